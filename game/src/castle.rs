@@ -9,56 +9,74 @@ pub enum Side {
     Queenside,
 }
 impl Side {
-    pub fn require_empty_squares(self, c: Color) -> Vec<Square> {
-        match (c, self) {
-            (White, Kingside)  => vec![Square::new(File(5), Rank(0)),
-                                       Square::new(File(6), Rank(0))],
-            (White, Queenside) => vec![Square::new(File(3), Rank(0)),
-                                       Square::new(File(2), Rank(0)),
-                                       Square::new(File(1), Rank(0))],
-            (Black, Kingside)  => vec![Square::new(File(5), Rank(7)),
-                                       Square::new(File(6), Rank(7))],
-            (Black, Queenside) => vec![Square::new(File(3), Rank(7)),
-                                       Square::new(File(2), Rank(7)),
-                                       Square::new(File(1), Rank(7))],
+    /// The file that the king ends up on, which is the same as in standard chess.
+    pub fn king_to_file(self) -> File {
+        match self {
+            Kingside  => File(6),
+            Queenside => File(2),
         }
     }
-
-    pub fn require_no_attack(self, c: Color) -> Vec<Square> {
-        match (c, self) {
-            (White, Kingside)  => vec![Square::new(File(4), Rank(0)),
-                                       Square::new(File(5), Rank(0)),
-                                       Square::new(File(6), Rank(0))],
-            (White, Queenside) => vec![Square::new(File(4), Rank(0)),
-                                       Square::new(File(3), Rank(0)),
-                                       Square::new(File(2), Rank(0))],
-            (Black, Kingside)  => vec![Square::new(File(4), Rank(7)),
-                                       Square::new(File(5), Rank(7)),
-                                       Square::new(File(6), Rank(7))],
-            (Black, Queenside) => vec![Square::new(File(4), Rank(7)),
-                                       Square::new(File(3), Rank(7)),
-                                       Square::new(File(2), Rank(7))],
+    /// The file that the rook ends up on.
+    pub fn rook_to_file(self) -> File {
+        match self {
+            Kingside  => File(5),
+            Queenside => File(3),
         }
     }
+
+    /// The squares that must be empty for the king on king_file to castle
+    /// with the rook on rook_file. In Chess960 the king and the rook can start
+    /// anywhere on the back rank, so these are all the squares that either of them
+    /// passes through or lands on, other than where they start.
+    pub fn require_empty_squares(self, c: Color, king_file: File, rook_file: File) -> Vec<Square> {
+        let files = [king_file.0, self.king_to_file().0, rook_file.0, self.rook_to_file().0];
+        let min = *files.iter().min().unwrap();
+        let max = *files.iter().max().unwrap();
+        (min..max+1).filter(|&x| x != king_file.0 && x != rook_file.0)
+                    .map(|x| Square::new(File(x), back_rank(c)))
+                    .collect()
+    }
+
+    /// The squares that the king passes through or lands on,
+    /// which must not be attacked, including the one it starts on.
+    pub fn require_no_attack(self, c: Color, king_file: File) -> Vec<Square> {
+        let (from, to) = (king_file.0, self.king_to_file().0);
+        let files: Vec<i32> = if from <= to { (from..to+1).collect() }
+                              else { (to..from+1).rev().collect() };
+        files.into_iter().map(|x| Square::new(File(x), back_rank(c))).collect()
+    }
 }
 
+/// The rank that the pieces of the color start on.
+pub fn back_rank(c: Color) -> Rank {
+    match c {
+        White => Rank(0),
+        Black => Rank(7),
+    }
+}
+
+/// The castling rights. For each right, the file of the rook that castles is kept,
+/// since in Chess960 it is not always on the a or h file.
 #[derive(PartialEq, Eq, Copy, Clone, Debug)]
 pub struct CastlingData {
-    w_kingside: bool,
-    w_queenside: bool,
-    b_kingside: bool,
-    b_queenside: bool,
+    w_kingside: Option<File>,
+    w_queenside: Option<File>,
+    b_kingside: Option<File>,
+    b_queenside: Option<File>,
 }
 impl CastlingData {
     pub fn new() -> CastlingData {
         CastlingData {
-            w_kingside: false,
-            w_queenside: false,
-            b_kingside: false,
-            b_queenside: false,
+            w_kingside: None,
+            w_queenside: None,
+            b_kingside: None,
+            b_queenside: None,
         }
     }
     pub fn get(&self, side: Side, c: Color) -> bool {
+        self.rook_file(side, c).is_some()
+    }
+    pub fn rook_file(&self, side: Side, c: Color) -> Option<File> {
         match (c, side) {
             (White, Kingside)  => self.w_kingside,
             (White, Queenside) => self.w_queenside,
@@ -66,7 +84,7 @@ impl CastlingData {
             (Black, Queenside) => self.b_queenside,
         }
     }
-    pub fn set(&mut self, side: Side, c: Color, val: bool) {
+    pub fn set(&mut self, side: Side, c: Color, val: Option<File>) {
         match (c, side) {
             (White, Kingside)  => self.w_kingside  = val,
             (White, Queenside) => self.w_queenside = val,
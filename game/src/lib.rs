@@ -12,17 +12,22 @@ pub use color::{Color, White, Black};
 pub use piece::Piece;
 pub use piece::Type as PieceType;
 pub use piece::Type::*;
-pub use moves::{Move, FromTo};
+pub use moves::{Move, PackedMove, FromTo};
 pub use moves::{NumPlies, NumMoves};
+pub use move_list::MoveList;
 
 pub use pos::Position;
 pub use pos::ExtraData as PosExtraData;
 pub use pos::{ScoreUnit, Score};
+pub use pos::{EvalParams, PhaseScore};
 pub use pos::ZobristHash;
+pub use pos::BitBoard;
+pub use pos::{ParsePosError, ParseSanError};
 
 mod color;
 mod piece;
 mod square;
 mod moves;
+mod move_list;
 mod castle;
 mod pos;
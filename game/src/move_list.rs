@@ -0,0 +1,73 @@
+//! A list of moves kept on the stack, so that generating moves does not allocate.
+
+use std::ops::{Deref, DerefMut};
+use std::slice;
+
+use moves::Move;
+use square::Square;
+
+/// No position has more legal moves than this.
+pub const MAX_MOVES: usize = 256;
+
+pub struct MoveList {
+    moves: [Move; MAX_MOVES],
+    len: usize,
+}
+impl MoveList {
+    pub fn new() -> MoveList {
+        let a1 = Square::from_id(0);
+        MoveList {
+            moves: [Move::new(a1, a1); MAX_MOVES],
+            len: 0,
+        }
+    }
+
+    pub fn push(&mut self, m: Move) {
+        self.moves[self.len] = m;
+        self.len += 1;
+    }
+
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+}
+impl Default for MoveList {
+    fn default() -> MoveList { MoveList::new() }
+}
+impl Deref for MoveList {
+    type Target = [Move];
+    fn deref(&self) -> &[Move] { &self.moves[..self.len] }
+}
+impl DerefMut for MoveList {
+    fn deref_mut(&mut self) -> &mut [Move] { &mut self.moves[..self.len] }
+}
+impl<'a> IntoIterator for &'a MoveList {
+    type Item = &'a Move;
+    type IntoIter = slice::Iter<'a, Move>;
+    fn into_iter(self) -> slice::Iter<'a, Move> { self.iter() }
+}
+impl IntoIterator for MoveList {
+    type Item = Move;
+    type IntoIter = IntoIter;
+    fn into_iter(self) -> IntoIter { IntoIter { list: self, next: 0 } }
+}
+
+pub struct IntoIter {
+    list: MoveList,
+    next: usize,
+}
+impl Iterator for IntoIter {
+    type Item = Move;
+    fn next(&mut self) -> Option<Move> {
+        if self.next < self.list.len {
+            self.next += 1;
+            Some(self.list.moves[self.next - 1])
+        } else {
+            None
+        }
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let res = self.list.len - self.next;
+        (res, Some(res))
+    }
+}
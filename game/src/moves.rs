@@ -2,11 +2,12 @@
 
 use std::str::FromStr;
 use std::fmt;
+use std::num::NonZeroU16;
 
 use piece::{self, Piece, Queen, Bishop, Knight, Rook, King, Pawn};
 use square::{Square, File, ParseSquareError};
 use castle::{Side, Kingside, Queenside};
-use pos::{Position, at_in_pos, is_empty_at_in_pos};
+use pos::{Position, at_in_pos, is_empty_at_in_pos, move_to_san_in_pos};
 
 #[derive(PartialEq, Eq, PartialOrd, Ord, Copy, Clone, Debug)]
 pub struct NumPlies(pub u32);
@@ -14,7 +15,7 @@ pub struct NumPlies(pub u32);
 #[derive(PartialEq, Eq, PartialOrd, Ord, Copy, Clone, Debug)]
 pub struct NumMoves(pub u32);
 
-#[derive(PartialEq, Eq, Clone, Debug)]
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
 pub struct Move {
     from: Square,
     to: Square,
@@ -23,6 +24,8 @@ pub struct Move {
     is_en_passant: bool,
     promote: Option<piece::Type>,
     is_pawn_double_move: bool,
+    // Castling moves are written as the king taking its own rook if set.
+    chess960: bool,
 }
 impl Move {
     pub fn new(from: Square, to: Square) -> Move {
@@ -34,6 +37,7 @@ impl Move {
             is_en_passant: false,
             promote: None,
             is_pawn_double_move: false,
+            chess960: false,
         }
     }
 
@@ -45,11 +49,26 @@ impl Move {
         self.capture_normal = val;
     }
 
+    /// For castling moves, to() is the square of the rook that castles,
+    /// which works for Chess960 as well.
     pub fn castle(&self) -> Option<Side> { self.castle }
     pub fn set_castle(&mut self, val: Option<Side>) {
         self.castle = val;
     }
 
+    pub fn is_chess960(&self) -> bool { self.chess960 }
+    pub fn set_chess960(&mut self, val: bool) {
+        self.chess960 = val;
+    }
+
+    /// Where the king ends up, which for castling moves is not to().
+    pub fn king_to(&self) -> Square {
+        match self.castle {
+            Some(side) => Square::new(side.king_to_file(), self.from.rank()),
+            None => self.to,
+        }
+    }
+
     pub fn is_en_passant(&self) -> bool { self.is_en_passant }
     pub fn set_en_passant(&mut self, val: bool) {
         self.is_en_passant = val;
@@ -71,10 +90,33 @@ impl Move {
     pub fn is_quiet(&self) -> bool {
         !self.is_noisy()
     }
+
+    /// Packs the from square, the to square and the promotion into 16 bits.
+    pub fn pack(&self) -> PackedMove {
+        let promote_bits = match self.promote {
+            None => 0,
+            Some(Queen) => 1,
+            Some(Rook) => 2,
+            Some(Bishop) => 3,
+            Some(Knight) => 4,
+            Some(_) => unreachable!(),
+        };
+        let val = (self.from.to_id() as u16) | ((self.to.to_id() as u16) << 6) |
+                  (promote_bits << 12);
+        // The from and to squares are never the same, so val is never 0.
+        PackedMove(NonZeroU16::new(val).unwrap())
+    }
+
+    /// Formats a legal move of the given position in Standard Algebraic Notation.
+    pub fn to_san(&self, pos: &Position) -> String {
+        move_to_san_in_pos(pos, self)
+    }
 }
 impl fmt::Display for Move {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        try!(write!(f, "{}{}", self.from, self.to));
+        // Castling is written as the king moving two squares, unless in Chess960.
+        let to = if self.chess960 { self.to } else { self.king_to() };
+        try!(write!(f, "{}{}", self.from, to));
         if let Some(val) = self.promote() {
             try!(write!(f, "{}", match val {
                 Queen  => 'q',
@@ -88,6 +130,23 @@ impl fmt::Display for Move {
     }
 }
 
+/// A move packed into 16 bits by Move::pack(), for keeping many moves in little memory.
+/// Position::unpack_move() recovers the move from the position it was made in.
+/// Option<PackedMove> also takes 16 bits.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub struct PackedMove(NonZeroU16);
+impl PackedMove {
+    pub fn from_u16(val: u16) -> Option<PackedMove> {
+        NonZeroU16::new(val).map(PackedMove)
+    }
+    pub fn to_u16(self) -> u16 {
+        self.0.get()
+    }
+
+    pub fn from(self) -> Square { Square::from_id((self.to_u16() & 63) as i32) }
+    pub fn to(self) -> Square { Square::from_id(((self.to_u16() >> 6) & 63) as i32) }
+}
+
 pub struct ParseFromToError(());
 impl From<ParseSquareError> for ParseFromToError {
     fn from(_: ParseSquareError) -> Self { ParseFromToError(()) }
@@ -109,12 +168,27 @@ impl FromTo {
         if !is_empty_at_in_pos(pos, self.to) {
             ans.set_capture_normal(at_in_pos(pos, self.to));
         }
-        match at_in_pos(pos, self.from).map(|x| x.piece_type()) {
+        let moved = at_in_pos(pos, self.from);
+        match moved.map(|x| x.piece_type()) {
             Some(King) => {
-                match (self.from.file(), self.to.file()) {
-                    (File(4), File(6)) => ans.set_castle(Some(Kingside)),
-                    (File(4), File(2)) => ans.set_castle(Some(Queenside)),
-                    _ => {},
+                let side = if self.to.file().0 > self.from.file().0 { Kingside } else { Queenside };
+                let rank = self.from.rank();
+                if at_in_pos(pos, self.to) == moved.map(|x| Piece::new(x.color(), Rook)) {
+                    // The king takes its own rook.
+                    ans = Move::new(self.from, self.to);
+                    ans.set_castle(Some(side));
+                } else if !pos.is_chess960() && self.from.file() == File(4) &&
+                          (self.to.file() == File(6) || self.to.file() == File(2)) {
+                    // Outside of Chess960, the rooks castle from the corners.
+                    let rook_file = match side {
+                        Kingside => File(7),
+                        Queenside => File(0),
+                    };
+                    ans = Move::new(self.from, Square::new(rook_file, rank));
+                    ans.set_castle(Some(side));
+                }
+                if ans.castle().is_some() {
+                    ans.set_chess960(pos.is_chess960());
                 }
             },
             Some(Pawn) => {
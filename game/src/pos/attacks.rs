@@ -0,0 +1,277 @@
+//! Precomputed attack tables. The attacks of sliders are looked up with magic bitboards.
+
+use square::{Square, File, Rank};
+use color::{Color, White, Black};
+use piece::{self, Piece, Pawn, King, Queen, Bishop, Knight, Rook};
+
+use super::Position;
+use super::bitboard::BitBoard;
+
+// (file, rank)
+pub type Diff = (i32, i32);
+
+pub fn shift(s: Square, dir: Diff) -> Option<Square> {
+    let (dx, dy) = dir;
+    let (File(file), Rank(rank)) = s.to_tuple();
+    Square::from_i32(file + dx, rank + dy)
+}
+
+static ROOK_SLIDE:   [Diff; 4] = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+static BISHOP_SLIDE: [Diff; 4] = [(1, 1), (1, -1), (-1, -1), (-1, 1)];
+
+static KING_FIXED:   [Diff; 8] = [(1, 0), (0, 1), (-1, 0), (0, -1),
+                                  (1, 1), (1, -1), (-1, -1), (-1, 1)];
+static KNIGHT_FIXED: [Diff; 8] = [(2, 1), (2, -1), (-2, -1), (-2, 1),
+                                  (1, 2), (1, -2), (-1, -2), (-1, 2)];
+
+lazy_static! {
+    static ref KING_FIXED_TABLE: [BitBoard; 64] = fixed_table_gen(&KING_FIXED);
+    static ref KNIGHT_FIXED_TABLE: [BitBoard; 64] = fixed_table_gen(&KNIGHT_FIXED);
+    // Indexed by the color of the pawn.
+    static ref PAWN_TABLE: [[BitBoard; 64]; 2] = [fixed_table_gen(&[(1, 1), (-1, 1)]),
+                                                  fixed_table_gen(&[(1, -1), (-1, -1)])];
+    static ref SLIDER_TABLES: (MagicTable, MagicTable) =
+        (MagicTable::new(&ROOK_SLIDE), MagicTable::new(&BISHOP_SLIDE));
+    static ref LINE_TABLES: (Vec<[BitBoard; 64]>, Vec<[BitBoard; 64]>) = line_tables_gen();
+}
+
+// The squares between two squares, and the whole line through them.
+fn line_tables_gen() -> (Vec<[BitBoard; 64]>, Vec<[BitBoard; 64]>) {
+    let mut between = vec![[BitBoard::new(); 64]; 64];
+    let mut line = vec![[BitBoard::new(); 64]; 64];
+    let empty = BitBoard::new();
+    for a in 0..64 {
+        for b in 0..64 {
+            let (sa, sb) = (Square::from_id(a), Square::from_id(b));
+            for &piece_type in &[Rook, Bishop] {
+                if !slider_attacks(piece_type, sa, empty).at(sb) {
+                    continue;
+                }
+                let mut only_a = BitBoard::new();
+                only_a.set_at(sa);
+                let mut only_b = BitBoard::new();
+                only_b.set_at(sb);
+                between[a as usize][b as usize] = slider_attacks(piece_type, sa, only_b)
+                                                  .intersect(slider_attacks(piece_type, sb, only_a));
+                line[a as usize][b as usize] = slider_attacks(piece_type, sa, empty)
+                                               .intersect(slider_attacks(piece_type, sb, empty))
+                                               .union(only_a).union(only_b);
+            }
+        }
+    }
+    (between, line)
+}
+
+fn fixed_table_gen(diffs: &[Diff]) -> [BitBoard; 64] {
+    let mut ans = [BitBoard::new(); 64];
+    for i in 0..64 {
+        let from = Square::from_id(i);
+        for dir in diffs {
+            if let Some(to) = shift(from, *dir) {
+                ans[i as usize].set_at(to);
+            }
+        }
+    }
+    ans
+}
+
+/// The squares that a slider of the type on `from` attacks
+/// if `occupied` are the squares that are not empty.
+pub fn slider_attacks(piece_type: piece::Type, from: Square, occupied: BitBoard) -> BitBoard {
+    let (ref rook, ref bishop) = *SLIDER_TABLES;
+    match piece_type {
+        Rook => rook.get(from, occupied),
+        Bishop => bishop.get(from, occupied),
+        Queen => rook.get(from, occupied).union(bishop.get(from, occupied)),
+        _ => panic!(),
+    }
+}
+
+/// The squares that a king or a knight on `from` attacks.
+pub fn fixed_attacks(piece_type: piece::Type, from: Square) -> BitBoard {
+    let table: &[BitBoard; 64] = match piece_type {
+        King => &KING_FIXED_TABLE,
+        Knight => &KNIGHT_FIXED_TABLE,
+        _ => panic!(),
+    };
+    table[from.to_id() as usize]
+}
+
+/// The squares that a pawn of color c on `from` attacks.
+pub fn pawn_attacks(c: Color, from: Square) -> BitBoard {
+    PAWN_TABLE[c as usize][from.to_id() as usize]
+}
+
+/// The squares strictly between a and b if they are on the same rank, file or diagonal,
+/// and no squares otherwise.
+pub fn between(a: Square, b: Square) -> BitBoard {
+    LINE_TABLES.0[a.to_id() as usize][b.to_id() as usize]
+}
+
+/// All the squares of the rank, file or diagonal through a and b, if any.
+pub fn line(a: Square, b: Square) -> BitBoard {
+    LINE_TABLES.1[a.to_id() as usize][b.to_id() as usize]
+}
+
+/// The pieces of both colors among `occupied` that attack s,
+/// if `occupied` are the squares that are not empty.
+/// Sliders behind other attackers are found once those attackers are removed from `occupied`.
+pub fn attackers_to(p: &Position, s: Square, occupied: BitBoard) -> BitBoard {
+    let pieces = |c: Color, piece_type: piece::Type| p.data.piece_data(Piece::new(c, piece_type));
+    let both = |piece_type: piece::Type| pieces(White, piece_type).union(pieces(Black, piece_type));
+
+    let straight = both(Rook).union(both(Queen))
+                             .intersect(slider_attacks(Rook, s, occupied));
+    let diagonal = both(Bishop).union(both(Queen))
+                               .intersect(slider_attacks(Bishop, s, occupied));
+    let knights = both(Knight).intersect(fixed_attacks(Knight, s));
+    let kings = both(King).intersect(fixed_attacks(King, s));
+    // A white pawn attacks s if a black pawn on s would attack the pawn, and the other way around.
+    let pawns = pieces(White, Pawn).intersect(pawn_attacks(Black, s))
+                .union(pieces(Black, Pawn).intersect(pawn_attacks(White, s)));
+
+    straight.union(diagonal).union(knights).union(kings).union(pawns).intersect(occupied)
+}
+
+/// Whether a piece of color c attacks s.
+pub fn is_attacked_by(p: &Position, s: Square, c: Color) -> bool {
+    attackers_to(p, s, p.occupied()).intersect(p.data.color_data(c)).count() != 0
+}
+
+// The attacks of a slider on a square are
+// attacks[offset + ((occupied & mask) * magic) >> shift],
+// where mask is the squares whose occupancy can block the slider.
+// The magic number is chosen so that sets of blockers with different attacks
+// never get the same index.
+struct Magic {
+    mask: u64,
+    magic: u64,
+    shift: u32,
+    offset: usize,
+}
+
+struct MagicTable {
+    magics: Vec<Magic>,
+    attacks: Vec<BitBoard>,
+}
+impl MagicTable {
+    fn new(diffs: &[Diff]) -> MagicTable {
+        let mut ans = MagicTable {
+            magics: Vec::with_capacity(64),
+            attacks: Vec::new(),
+        };
+        for i in 0..64 {
+            // These seeds find the magic numbers quickly, see find_magic().
+            let mut rng = Prng(MAGIC_SEEDS[(i / 8) as usize]);
+            let magic = find_magic(Square::from_id(i), diffs, &mut rng, &mut ans.attacks);
+            ans.magics.push(magic);
+        }
+        ans
+    }
+
+    fn get(&self, from: Square, occupied: BitBoard) -> BitBoard {
+        let m = &self.magics[from.to_id() as usize];
+        let idx = (occupied.to_u64() & m.mask).wrapping_mul(m.magic) >> m.shift;
+        self.attacks[m.offset + idx as usize]
+    }
+}
+
+// Walks along the rays, stopping at the first occupied square of each.
+fn slow_slider_attacks(from: Square, diffs: &[Diff], occupied: BitBoard) -> BitBoard {
+    let mut ans = BitBoard::new();
+    for &dir in diffs {
+        let mut to = from;
+        while let Some(temp) = shift(to, dir) {
+            to = temp;
+            ans.set_at(to);
+            if occupied.at(to) {
+                break;
+            }
+        }
+    }
+    ans
+}
+
+// The rays without their last square, since a piece there cannot block anything.
+fn blocker_mask(from: Square, diffs: &[Diff]) -> BitBoard {
+    let mut ans = BitBoard::new();
+    for &dir in diffs {
+        let mut to = from;
+        while let Some(temp) = shift(to, dir) {
+            if shift(temp, dir).is_none() {
+                break;
+            }
+            to = temp;
+            ans.set_at(to);
+        }
+    }
+    ans
+}
+
+// A xorshift generator, which is much faster than the ones from rand.
+// Finding the magic numbers takes millions of random numbers.
+struct Prng(u64);
+impl Prng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(2685821657736338717)
+    }
+}
+
+// The seeds used by Stockfish for each rank. Its squares are numbered rank first
+// while ours are numbered file first, but the attacks of sliders are the same
+// when the board is mirrored along the a1-h8 diagonal, so they work as well here.
+static MAGIC_SEEDS: [u64; 8] = [728, 10316, 55013, 32803, 12281, 15100, 16645, 255];
+
+// Finds a magic number for the square by trial and error,
+// and adds its attacks to the end of `attacks`.
+fn find_magic(from: Square, diffs: &[Diff], rng: &mut Prng,
+              attacks: &mut Vec<BitBoard>) -> Magic {
+    let mask = blocker_mask(from, diffs).to_u64();
+    let bits = mask.count_ones();
+
+    // Goes through all subsets of the mask.
+    let mut blockers = Vec::with_capacity(1 << bits);
+    let mut subset = 0_u64;
+    loop {
+        blockers.push((subset, slow_slider_attacks(from, diffs, BitBoard::from_u64(subset))));
+        subset = subset.wrapping_sub(mask) & mask;
+        if subset == 0 {
+            break;
+        }
+    }
+
+    // An entry of the table is only filled if it was set during the current try,
+    // so that the table does not need to be cleared between the many tries.
+    let mut table: Vec<(u32, BitBoard)> = vec![(0, BitBoard::new()); 1 << bits];
+    let mut tries = 0;
+    loop {
+        // Numbers with few bits set are more likely to work.
+        let magic = rng.next() & rng.next() & rng.next();
+        if (mask.wrapping_mul(magic) >> 56).count_ones() < 6 {
+            continue;
+        }
+        tries += 1;
+        let works = blockers.iter().all(|&(subset, val)| {
+            let entry = &mut table[(subset.wrapping_mul(magic) >> (64 - bits)) as usize];
+            if entry.0 != tries {
+                *entry = (tries, val);
+                true
+            } else {
+                entry.1 == val
+            }
+        });
+        if works {
+            let offset = attacks.len();
+            attacks.extend(table.into_iter().map(|x| x.1));
+            return Magic {
+                mask: mask,
+                magic: magic,
+                shift: 64 - bits,
+                offset: offset,
+            };
+        }
+    }
+}
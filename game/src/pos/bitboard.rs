@@ -4,11 +4,14 @@ use std::ops::Not;
 
 use square::Square;
 
-#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+#[derive(PartialEq, Eq, Copy, Clone, Debug, Default)]
 pub struct BitBoard(u64);
 impl BitBoard {
     pub fn new() -> BitBoard { BitBoard(0_u64) }
     pub fn new_full() -> BitBoard { BitBoard(!0_u64) }
+    // Bit i is set for the square with id i.
+    pub fn from_u64(val: u64) -> BitBoard { BitBoard(val) }
+    pub fn to_u64(self) -> u64 { self.0 }
 
     pub fn at(self, s: Square) -> bool {
         self.0 & (1_u64 << s.to_id()) != 0
@@ -22,6 +25,10 @@ impl BitBoard {
         self.0 ^= 1_u64 << s.to_id();
     }
 
+    pub fn count(self) -> u32 {
+        self.0.count_ones()
+    }
+
     pub fn iter(self) -> Iter {
         Iter(self.0)
     }
@@ -29,6 +36,9 @@ impl BitBoard {
     pub fn intersect(self, other: BitBoard) -> BitBoard {
         BitBoard(self.0 & other.0)
     }
+    pub fn union(self, other: BitBoard) -> BitBoard {
+        BitBoard(self.0 | other.0)
+    }
 }
 impl Not for BitBoard {
     type Output = BitBoard;
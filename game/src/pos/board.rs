@@ -24,7 +24,7 @@ impl Board {
         }
     }
 
-    fn piece_data(&self, p: Piece) -> BitBoard {
+    pub fn piece_data(&self, p: Piece) -> BitBoard {
         self.data[p as usize]
     }
     fn piece_data_mut(&mut self, p: Piece) -> &mut BitBoard {
@@ -55,6 +55,7 @@ impl Board {
         }
         None
     }
+    pub fn piece_count(&self, p: Piece) -> u32 { self.piece_data(p).count() }
     pub fn is_piece_at(&self, p: Piece, s: Square) -> bool { self.piece_data(p).at(s) }
     pub fn is_empty_at(&self, s: Square) -> bool { self.empty_data.at(s) }
     pub fn is_color_at(&self, s: Square, c: Color) -> bool { self.color_data(c).at(s) }
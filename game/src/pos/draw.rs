@@ -0,0 +1,23 @@
+use super::{Position, ZobristHash};
+
+pub fn is_draw_by_fifty_move_rule(p: &mut Position) -> bool {
+    // Checkmate on the hundredth ply takes precedence over the draw.
+    p.ply_count().0 >= 100 && !p.is_checkmated()
+}
+
+pub fn repetition_count(p: &Position, history: &[ZobristHash]) -> usize {
+    let hash = p.hash();
+    // Only positions since the last capture or pawn move can be repeated,
+    // and only every other ply has the same side to move.
+    history.iter()
+           .rev()
+           .take(p.ply_count().0 as usize)
+           .skip(1)
+           .step_by(2)
+           .filter(|&&x| x == hash)
+           .count()
+}
+
+pub fn is_draw_by_rule(p: &mut Position, history: &[ZobristHash]) -> bool {
+    repetition_count(p, history) >= 2 || is_draw_by_fifty_move_rule(p)
+}
@@ -2,12 +2,18 @@
 
 use std::fmt;
 use std::ops::{Add, Sub, Neg, Mul};
-use std::cmp::Ordering;
+use std::cmp::{Ordering, min};
+use std::cell::RefCell;
 
 use moves::NumMoves;
-use {Color, Piece, PieceType};
-use {Pawn, King, Queen, Bishop, Knight, Rook};
+use square::{Square, File, Rank};
+use piece;
+use {Color, White, Black, Piece, Pawn, Knight, Bishop, Rook, Queen, King};
 use super::Position;
+use super::bitboard::BitBoard;
+use super::attacks::{shift, slider_attacks, fixed_attacks, pawn_attacks};
+use super::eval_params::{EvalParams, PhaseScore, MAX_PHASE, phase_of_piece_type};
+use super::pawns::{PawnTable, pawn_structure};
 
 #[derive(PartialEq, Eq, PartialOrd, Ord, Copy, Clone, Debug)]
 pub struct ScoreUnit(pub i32);
@@ -94,45 +100,138 @@ impl PartialOrd for Score {
     }
 }
 
+lazy_static! {
+    static ref DEFAULT_PARAMS: EvalParams = EvalParams::default();
+    static ref PIECE_SQUARE_VALUES: [[PhaseScore; 64]; 12] = {
+        let mut ans = [[PhaseScore::zero(); 64]; 12];
+        for &piece in piece::ALL.iter() {
+            for id in 0..64 {
+                ans[piece as usize][id as usize] =
+                    DEFAULT_PARAMS.piece_value(piece, Square::from_id(id));
+            }
+        }
+        ans
+    };
+}
+
+/// The material and piece-square value of the piece on the square, from white's point of view.
+/// Position keeps the sum of these up to date as pieces are added and removed.
+pub fn piece_square_value(piece: Piece, s: Square) -> PhaseScore {
+    PIECE_SQUARE_VALUES[piece as usize][s.to_id() as usize]
+}
+
+thread_local! {
+    // Only used with DEFAULT_PARAMS.
+    static PAWN_TABLE: RefCell<PawnTable> = RefCell::new(PawnTable::new());
+}
+
 /// Evaluates the position without searching.
 pub fn eval(p: &mut Position) -> Score {
+    let (piece_score, phase) = (p.piece_score(), p.phase());
+    evaluate(p, &DEFAULT_PARAMS, piece_score, phase, |p| {
+        PAWN_TABLE.with(|table| table.borrow_mut().get(p, &DEFAULT_PARAMS))
+    })
+}
+
+/// Like eval(), but with the given weights instead of the default ones.
+/// Nothing is cached or updated incrementally, so this is much slower.
+pub fn eval_with(p: &mut Position, params: &EvalParams) -> Score {
+    let mut phase = 0;
+    let mut piece_score = PhaseScore::zero();
+    for (piece, s) in p.piece_iter() {
+        phase += phase_of_piece_type(piece.piece_type());
+        piece_score = piece_score + params.piece_value(piece, s);
+    }
+    evaluate(p, params, piece_score, phase, |p| pawn_structure(p, params))
+}
+
+// piece_score is the sum of the material and piece-square values from white's point of view,
+// and phase is the sum of the phase of the pieces.
+// pawn_fn gives the pawn structure score from white's point of view.
+fn evaluate<F>(p: &mut Position, params: &EvalParams,
+               piece_score: PhaseScore, phase: i32, pawn_fn: F) -> Score
+where F: FnOnce(&Position) -> PhaseScore {
     if p.is_checkmated() {
         Score::LoseIn(NumMoves(0))
     } else if p.is_stalemated() {
         Score::Value(ScoreUnit(0))
     } else {
         let c = p.side_to_move();
-        // TODO change fold() to sum() when possible
-        let piece_eval = p.piece_iter()
-                          .map( |(piece, _pos)| val_for_color(piece, c) )
-                          .fold(ScoreUnit(0), |x, y| x+y);
-        let our_mobility = p.psudo_legal_iter().count();
-        p.swap_side_to_move();
-        let his_mobility = p.psudo_legal_iter().count();
-        p.swap_side_to_move();
-        let mobility_diff = our_mobility as i32- his_mobility as i32;
-        Score::Value(piece_eval + VALUE_PER_MOBILITY * (mobility_diff))
+        let mut total = if c == White { piece_score } else { -piece_score };
+        // There may be more pieces than at the start after promotions.
+        let phase = min(phase, MAX_PHASE);
+
+        let (our_mobility, our_attacks) = mobility_and_king_attacks(p, c);
+        let (his_mobility, his_attacks) = mobility_and_king_attacks(p, c.invert());
+        total = total + params.mobility * (our_mobility - his_mobility);
+        // Attacks near our king are counted in his_attacks.
+        total = total + params.king_attack * (his_attacks - our_attacks);
+        total = total + params.king_shelter * (king_shelter(p, c) - king_shelter(p, c.invert()));
+        let pawn_score = pawn_fn(p);
+        total = total + if c == White { pawn_score } else { -pawn_score };
+
+        Score::Value(total.taper(phase))
     }
 }
 
-fn val_for_color(piece: Piece, c: Color) -> ScoreUnit {
-    let val = val_of_piece_type(piece.piece_type());
-    if piece.color() == c {
-        val
-    } else {
-        -val
+// The number of squares that the pieces of color c can move to, counted once for
+// each piece, and how many of them are next to the king of the other side.
+fn mobility_and_king_attacks(p: &Position, c: Color) -> (i32, i32) {
+    let occupied = !p.data.empty_data();
+    let not_ours = !p.data.color_data(c);
+    let king = p.king_square(c.invert());
+    let mut king_zone = fixed_attacks(King, king);
+    king_zone.set_at(king);
+
+    let mut mobility = 0;
+    let mut attacks = 0;
+    let mut add = |targets: BitBoard| {
+        mobility += targets.count() as i32;
+        attacks += targets.intersect(king_zone).count() as i32;
+    };
+    for &piece_type in &[Knight, King] {
+        for from in p.data.piece_data(Piece::new(c, piece_type)).iter() {
+            add(fixed_attacks(piece_type, from).intersect(not_ours));
+        }
+    }
+    for &piece_type in &[Bishop, Rook, Queen] {
+        for from in p.data.piece_data(Piece::new(c, piece_type)).iter() {
+            add(slider_attacks(piece_type, from, occupied).intersect(not_ours));
+        }
     }
+    // Pawns capture the pieces of the other side, and move forward to an empty square.
+    let theirs = p.data.color_data(c.invert());
+    let forward = if c == White { 1 } else { -1 };
+    for from in p.data.piece_data(Piece::new(c, Pawn)).iter() {
+        let mut targets = pawn_attacks(c, from).intersect(theirs);
+        if let Some(to) = shift(from, (0, forward)) {
+            if p.is_empty_at(to) {
+                targets.set_at(to);
+            }
+        }
+        add(targets);
+    }
+    (mobility, attacks)
 }
 
-const VALUE_PER_MOBILITY: ScoreUnit = ScoreUnit(5);
-
-fn val_of_piece_type(piece_type: PieceType) -> ScoreUnit {
-    ScoreUnit(match piece_type {
-        King   => 100000,
-        Pawn   =>    100,
-        Queen  =>    900,
-        Bishop =>    300,
-        Knight =>    300,
-        Rook   =>    500,
-    })
+// The number of pawns of color c on the two ranks in front of its king,
+// on the king file and the files next to it.
+fn king_shelter(p: &Position, c: Color) -> i32 {
+    let (File(king_file), Rank(king_rank)) = p.king_square(c).to_tuple();
+    let pawn = Piece::new(c, Pawn);
+    let forward = match c {
+        White => 1,
+        Black => -1,
+    };
+    let mut ans = 0;
+    for f in king_file-1 .. king_file+2 {
+        for dist in 1..3 {
+            if let Some(s) = Square::from_i32(f, king_rank + forward * dist) {
+                if p.is_piece_at(pawn, s) {
+                    ans += 1;
+                }
+            }
+        }
+    }
+    ans
 }
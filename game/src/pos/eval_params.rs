@@ -0,0 +1,320 @@
+//! The weights used by the evaluation, kept apart from the code that uses them
+//! so that they can be replaced or tuned.
+
+use std::ops::{Add, Sub, Neg, Mul};
+
+use color::{Color, White, Black};
+use piece::Piece;
+use piece::Type as PieceType;
+use square::{Square, File, Rank};
+
+use super::eval::ScoreUnit;
+
+/// A pair of middle game and end game values, which are blended by the game phase.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub struct PhaseScore(pub i32, pub i32);
+impl PhaseScore {
+    pub fn zero() -> PhaseScore { PhaseScore(0, 0) }
+
+    /// `phase` is MAX_PHASE in the opening, and goes down to 0 as pieces are traded.
+    pub fn taper(self, phase: i32) -> ScoreUnit {
+        let PhaseScore(mg, eg) = self;
+        ScoreUnit((mg * phase + eg * (MAX_PHASE - phase)) / MAX_PHASE)
+    }
+}
+impl Add for PhaseScore {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self { PhaseScore(self.0 + rhs.0, self.1 + rhs.1) }
+}
+impl Sub for PhaseScore {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self { PhaseScore(self.0 - rhs.0, self.1 - rhs.1) }
+}
+impl Neg for PhaseScore {
+    type Output = Self;
+    fn neg(self) -> Self { PhaseScore(-self.0, -self.1) }
+}
+impl Mul<i32> for PhaseScore {
+    type Output = Self;
+    fn mul(self, rhs: i32) -> Self { PhaseScore(self.0 * rhs, self.1 * rhs) }
+}
+
+/// The phase of the starting position.
+pub const MAX_PHASE: i32 = 24;
+
+/// How much each piece of the type counts towards the game phase.
+pub fn phase_of_piece_type(piece_type: PieceType) -> i32 {
+    match piece_type {
+        PieceType::Pawn   => 0,
+        PieceType::King   => 0,
+        PieceType::Queen  => 4,
+        PieceType::Bishop => 1,
+        PieceType::Knight => 1,
+        PieceType::Rook   => 2,
+    }
+}
+
+/// All the weights of the evaluation.
+///
+/// The tables are indexed by `PieceType as usize`.
+/// The piece-square tables are from white's point of view, with a8 first and h1 last,
+/// and are mirrored for black.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct EvalParams {
+    pub material: [PhaseScore; 6],
+    pub piece_square: [[PhaseScore; 64]; 6],
+    // Per pseudo-legal move.
+    pub mobility: PhaseScore,
+    // Per own pawn in front of the king.
+    pub king_shelter: PhaseScore,
+    // Per pseudo-legal move of the opponent to a square next to the king.
+    pub king_attack: PhaseScore,
+    // Indexed by the rank of the passed pawn counted from its own side, 1 to 6.
+    pub passed: [PhaseScore; 8],
+    // Added to passed for a passed pawn with another one on a file next to it.
+    pub connected_passer: PhaseScore,
+    // Added to passed for a passed pawn defended by a pawn.
+    pub protected_passer: PhaseScore,
+    pub isolated: PhaseScore,
+    // For each pawn with another pawn of its color in front of it.
+    pub doubled: PhaseScore,
+    pub backward: PhaseScore,
+}
+impl EvalParams {
+    pub fn piece_square(&self, piece_type: PieceType, c: Color, s: Square) -> PhaseScore {
+        let (File(f), Rank(r)) = s.to_tuple();
+        let row = match c {
+            White => 7 - r,
+            Black => r,
+        };
+        self.piece_square[piece_type as usize][(row * 8 + f) as usize]
+    }
+    /// The material and piece-square value from white's point of view.
+    pub fn piece_value(&self, piece: Piece, s: Square) -> PhaseScore {
+        let piece_type = piece.piece_type();
+        let val = self.material[piece_type as usize] +
+                  self.piece_square(piece_type, piece.color(), s);
+        match piece.color() {
+            White => val,
+            Black => -val,
+        }
+    }
+
+    /// All the weights in a fixed order, with the middle game value of each first.
+    pub fn to_vec(&self) -> Vec<i32> {
+        let mut ans = Vec::with_capacity(NUM_VALUES);
+        {
+            let mut push = |x: PhaseScore| { ans.push(x.0); ans.push(x.1); };
+            for x in self.material.iter() { push(*x); }
+            for table in self.piece_square.iter() {
+                for x in table.iter() { push(*x); }
+            }
+            push(self.mobility);
+            push(self.king_shelter);
+            push(self.king_attack);
+            for x in self.passed.iter() { push(*x); }
+            push(self.connected_passer);
+            push(self.protected_passer);
+            push(self.isolated);
+            push(self.doubled);
+            push(self.backward);
+        }
+        ans
+    }
+    /// The inverse of `to_vec()`. Returns None if there are not exactly enough values.
+    pub fn from_slice(values: &[i32]) -> Option<EvalParams> {
+        if values.len() != NUM_VALUES {
+            return None;
+        }
+        let mut iter = values.chunks(2).map(|x| PhaseScore(x[0], x[1]));
+        let mut ans = EvalParams {
+            material: [PhaseScore::zero(); 6],
+            piece_square: [[PhaseScore::zero(); 64]; 6],
+            mobility: PhaseScore::zero(),
+            king_shelter: PhaseScore::zero(),
+            king_attack: PhaseScore::zero(),
+            passed: [PhaseScore::zero(); 8],
+            connected_passer: PhaseScore::zero(),
+            protected_passer: PhaseScore::zero(),
+            isolated: PhaseScore::zero(),
+            doubled: PhaseScore::zero(),
+            backward: PhaseScore::zero(),
+        };
+        for x in ans.material.iter_mut() { *x = iter.next().unwrap(); }
+        for table in ans.piece_square.iter_mut() {
+            for x in table.iter_mut() { *x = iter.next().unwrap(); }
+        }
+        ans.mobility = iter.next().unwrap();
+        ans.king_shelter = iter.next().unwrap();
+        ans.king_attack = iter.next().unwrap();
+        for x in ans.passed.iter_mut() { *x = iter.next().unwrap(); }
+        ans.connected_passer = iter.next().unwrap();
+        ans.protected_passer = iter.next().unwrap();
+        ans.isolated = iter.next().unwrap();
+        ans.doubled = iter.next().unwrap();
+        ans.backward = iter.next().unwrap();
+        Some(ans)
+    }
+}
+impl Default for EvalParams {
+    fn default() -> EvalParams {
+        let mut piece_square = [[PhaseScore::zero(); 64]; 6];
+        for (piece_type, mg, eg) in [(PieceType::Pawn,   PAWN_MG,   PAWN_EG),
+                                     (PieceType::King,   KING_MG,   KING_EG),
+                                     (PieceType::Queen,  QUEEN_MG,  QUEEN_EG),
+                                     (PieceType::Bishop, BISHOP_MG, BISHOP_EG),
+                                     (PieceType::Knight, KNIGHT_MG, KNIGHT_EG),
+                                     (PieceType::Rook,   ROOK_MG,   ROOK_EG)] {
+            for i in 0..64 {
+                piece_square[piece_type as usize][i] = PhaseScore(mg[i], eg[i]);
+            }
+        }
+        let mut material = [PhaseScore::zero(); 6];
+        material[PieceType::Pawn as usize]   = PhaseScore(  90,  120);
+        material[PieceType::Queen as usize]  = PhaseScore( 900,  900);
+        material[PieceType::Bishop as usize] = PhaseScore( 330,  320);
+        material[PieceType::Knight as usize] = PhaseScore( 320,  300);
+        material[PieceType::Rook as usize]   = PhaseScore( 480,  520);
+        EvalParams {
+            material: material,
+            piece_square: piece_square,
+            mobility: PhaseScore(4, 4),
+            king_shelter: PhaseScore(12, 0),
+            king_attack: PhaseScore(-8, -1),
+            passed: [PhaseScore(0, 0),
+                     PhaseScore(5, 10),
+                     PhaseScore(5, 15),
+                     PhaseScore(10, 25),
+                     PhaseScore(20, 45),
+                     PhaseScore(35, 75),
+                     PhaseScore(60, 120),
+                     PhaseScore(0, 0)],
+            connected_passer: PhaseScore(10, 20),
+            protected_passer: PhaseScore(5, 15),
+            isolated: PhaseScore(-10, -15),
+            doubled: PhaseScore(-10, -20),
+            backward: PhaseScore(-8, -10),
+        }
+    }
+}
+
+const NUM_VALUES: usize = 2 * (6 + 6 * 64 + 3 + 8 + 5);
+
+// Most of the middle game tables are from the "Simplified Evaluation Function"
+// by Tomasz Michniewski.
+
+const PAWN_MG: [i32; 64] = [
+      0,   0,   0,   0,   0,   0,   0,   0,
+     50,  50,  50,  50,  50,  50,  50,  50,
+     10,  10,  20,  30,  30,  20,  10,  10,
+      5,   5,  10,  25,  25,  10,   5,   5,
+      0,   0,   0,  20,  20,   0,   0,   0,
+      5,  -5, -10,   0,   0, -10,  -5,   5,
+      5,  10,  10, -20, -20,  10,  10,   5,
+      0,   0,   0,   0,   0,   0,   0,   0];
+const PAWN_EG: [i32; 64] = [
+      0,   0,   0,   0,   0,   0,   0,   0,
+     80,  80,  80,  80,  80,  80,  80,  80,
+     50,  50,  50,  50,  50,  50,  50,  50,
+     30,  30,  30,  30,  30,  30,  30,  30,
+     15,  15,  15,  15,  15,  15,  15,  15,
+      5,   5,   5,   5,   5,   5,   5,   5,
+      0,   0,   0,   0,   0,   0,   0,   0,
+      0,   0,   0,   0,   0,   0,   0,   0];
+
+const KNIGHT_MG: [i32; 64] = [
+    -50, -40, -30, -30, -30, -30, -40, -50,
+    -40, -20,   0,   0,   0,   0, -20, -40,
+    -30,   0,  10,  15,  15,  10,   0, -30,
+    -30,   5,  15,  20,  20,  15,   5, -30,
+    -30,   0,  15,  20,  20,  15,   0, -30,
+    -30,   5,  10,  15,  15,  10,   5, -30,
+    -40, -20,   0,   5,   5,   0, -20, -40,
+    -50, -40, -30, -30, -30, -30, -40, -50];
+const KNIGHT_EG: [i32; 64] = [
+    -40, -30, -20, -20, -20, -20, -30, -40,
+    -30, -15,   0,   0,   0,   0, -15, -30,
+    -20,   0,  10,  15,  15,  10,   0, -20,
+    -20,   0,  15,  20,  20,  15,   0, -20,
+    -20,   0,  15,  20,  20,  15,   0, -20,
+    -20,   0,  10,  15,  15,  10,   0, -20,
+    -30, -15,   0,   0,   0,   0, -15, -30,
+    -40, -30, -20, -20, -20, -20, -30, -40];
+
+const BISHOP_MG: [i32; 64] = [
+    -20, -10, -10, -10, -10, -10, -10, -20,
+    -10,   0,   0,   0,   0,   0,   0, -10,
+    -10,   0,   5,  10,  10,   5,   0, -10,
+    -10,   5,   5,  10,  10,   5,   5, -10,
+    -10,   0,  10,  10,  10,  10,   0, -10,
+    -10,  10,  10,  10,  10,  10,  10, -10,
+    -10,   5,   0,   0,   0,   0,   5, -10,
+    -20, -10, -10, -10, -10, -10, -10, -20];
+const BISHOP_EG: [i32; 64] = [
+    -15, -10, -10, -10, -10, -10, -10, -15,
+    -10,   0,   0,   0,   0,   0,   0, -10,
+    -10,   0,   5,   5,   5,   5,   0, -10,
+    -10,   0,   5,  10,  10,   5,   0, -10,
+    -10,   0,   5,  10,  10,   5,   0, -10,
+    -10,   0,   5,   5,   5,   5,   0, -10,
+    -10,   0,   0,   0,   0,   0,   0, -10,
+    -15, -10, -10, -10, -10, -10, -10, -15];
+
+const ROOK_MG: [i32; 64] = [
+      0,   0,   0,   0,   0,   0,   0,   0,
+      5,  10,  10,  10,  10,  10,  10,   5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+      0,   0,   0,   5,   5,   0,   0,   0];
+const ROOK_EG: [i32; 64] = [
+      5,   5,   5,   5,   5,   5,   5,   5,
+     10,  10,  10,  10,  10,  10,  10,  10,
+      0,   0,   0,   0,   0,   0,   0,   0,
+      0,   0,   0,   0,   0,   0,   0,   0,
+      0,   0,   0,   0,   0,   0,   0,   0,
+      0,   0,   0,   0,   0,   0,   0,   0,
+      0,   0,   0,   0,   0,   0,   0,   0,
+      0,   0,   0,   0,   0,   0,   0,   0];
+
+const QUEEN_MG: [i32; 64] = [
+    -20, -10, -10,  -5,  -5, -10, -10, -20,
+    -10,   0,   0,   0,   0,   0,   0, -10,
+    -10,   0,   5,   5,   5,   5,   0, -10,
+     -5,   0,   5,   5,   5,   5,   0,  -5,
+      0,   0,   5,   5,   5,   5,   0,  -5,
+    -10,   5,   5,   5,   5,   5,   0, -10,
+    -10,   0,   5,   0,   0,   0,   0, -10,
+    -20, -10, -10,  -5,  -5, -10, -10, -20];
+const QUEEN_EG: [i32; 64] = [
+    -20, -10, -10,  -5,  -5, -10, -10, -20,
+    -10,   0,   0,   0,   0,   0,   0, -10,
+    -10,   0,   5,   5,   5,   5,   0, -10,
+     -5,   0,   5,  10,  10,   5,   0,  -5,
+     -5,   0,   5,  10,  10,   5,   0,  -5,
+    -10,   0,   5,   5,   5,   5,   0, -10,
+    -10,   0,   0,   0,   0,   0,   0, -10,
+    -20, -10, -10,  -5,  -5, -10, -10, -20];
+
+// The king hides behind its pawns in the middle game,
+// but should go to the center in the end game.
+const KING_MG: [i32; 64] = [
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -20, -30, -30, -40, -40, -30, -30, -20,
+    -10, -20, -20, -20, -20, -20, -20, -10,
+     20,  20,   0,   0,   0,   0,  20,  20,
+     20,  30,  10,   0,   0,  10,  30,  20];
+const KING_EG: [i32; 64] = [
+    -50, -40, -30, -20, -20, -30, -40, -50,
+    -30, -20, -10,   0,   0, -10, -20, -30,
+    -30, -10,  20,  30,  30,  20, -10, -30,
+    -30, -10,  30,  40,  40,  30, -10, -30,
+    -30, -10,  30,  40,  40,  30, -10, -30,
+    -30, -10,  20,  30,  30,  20, -10, -30,
+    -30, -30,   0,   0,   0,   0, -30, -30,
+    -50, -30, -30, -30, -30, -30, -30, -50];
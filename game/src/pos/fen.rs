@@ -1,96 +1,288 @@
-use std::collections::HashMap;
+use std::fmt;
 
-use moves::NumPlies;
+use moves::{NumPlies, NumMoves};
 use square::{File, Rank, Square};
-use castle::{Kingside, Queenside};
-use piece::Piece::*;
-use color::Color::{White, Black};
+use castle::{Side, Kingside, Queenside, back_rank};
+use piece::Piece::{self, WP, WK, WQ, WB, WN, WR, BP, BK, BQ, BB, BN, BR};
+use piece::{Pawn, King, Rook};
+use color::Color::{self, White, Black};
 
 use super::Position;
 
-#[derive(Debug)]
-pub struct ParsePosError(&'static str);
+/// The reason why a FEN string was rejected.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum ParsePosError {
+    WrongNumberOfFields,
+    WrongNumberOfRanks,
+    WrongRankLength,
+    InvalidPiece,
+    InvalidSideToMove,
+    InvalidCastling,
+    InvalidEnPassant,
+    InvalidPlyCount,
+    InvalidMoveCount,
+    WrongNumberOfKings,
+    PawnOnBackRank,
+    ImpossibleCastling,
+    ImpossibleEnPassant,
+    OpponentInCheck,
+}
+impl fmt::Display for ParsePosError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", match *self {
+            ParsePosError::WrongNumberOfFields => "FEN must have exactly 6 fields",
+            ParsePosError::WrongNumberOfRanks  => "board must have exactly 8 ranks",
+            ParsePosError::WrongRankLength     => "rank does not have exactly 8 squares",
+            ParsePosError::InvalidPiece        => "invalid character in board",
+            ParsePosError::InvalidSideToMove   => "invalid side to move",
+            ParsePosError::InvalidCastling     => "invalid castling rights",
+            ParsePosError::InvalidEnPassant    => "invalid en passant square",
+            ParsePosError::InvalidPlyCount     => "invalid halfmove clock",
+            ParsePosError::InvalidMoveCount    => "invalid fullmove number",
+            ParsePosError::WrongNumberOfKings  => "each side must have exactly one king",
+            ParsePosError::PawnOnBackRank      => "pawn on the first or eighth rank",
+            ParsePosError::ImpossibleCastling  => "castling rights without king and rook at home",
+            ParsePosError::ImpossibleEnPassant => "en passant square without a pushed pawn",
+            ParsePosError::OpponentInCheck     => "side not to move is in check",
+        })
+    }
+}
 
 pub fn start_pos() -> Position {
     fen_to_position("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap()
 }
 
-pub fn fen_to_position(fen: &str) -> Result<Position, ParsePosError> {
-    let fields: Vec<&str> = fen.split(' ').collect();
-    if fields.len() < 6 {
-        return Err(ParsePosError("Not enough fields in FEN input."));
+fn piece_from_char(ch: char) -> Option<Piece> {
+    Some(match ch {
+        'P' => WP, 'K' => WK, 'Q' => WQ, 'B' => WB, 'N' => WN, 'R' => WR,
+        'p' => BP, 'k' => BK, 'q' => BQ, 'b' => BB, 'n' => BN, 'r' => BR,
+        _ => return None,
+    })
+}
+
+fn piece_to_char(piece: Piece) -> char {
+    match piece {
+        WP => 'P', WK => 'K', WQ => 'Q', WB => 'B', WN => 'N', WR => 'R',
+        BP => 'p', BK => 'k', BQ => 'q', BB => 'b', BN => 'n', BR => 'r',
+    }
+}
+
+fn castle_char(side: Side, c: Color) -> char {
+    match (c, side) {
+        (White, Kingside)  => 'K',
+        (White, Queenside) => 'Q',
+        (Black, Kingside)  => 'k',
+        (Black, Queenside) => 'q',
+    }
+}
+
+// The file of the rook furthest from the king on the given side of it,
+// which K, Q, k and q refer to.
+fn outermost_rook_file(pos: &Position, side: Side, c: Color) -> Option<File> {
+    let king_file = pos.king_square(c).file().0;
+    let files: Vec<i32> = match side {
+        Kingside => (king_file+1..8).rev().collect(),
+        Queenside => (0..king_file).collect(),
     };
-    let mut pos_str = "".to_string();
-    for ch in fields[0].chars() {
-        match ch {
-            '0' ... '9' => {
-                let val = {
-                    match ch.to_digit(10) {
-                        Some(val) => val,
-                        None => return Err(ParsePosError("invalid character in fen")),
-                    }
-                };
-                for _ in 0..val {
-                    pos_str.push('1')
-                }
+    files.into_iter().map(File)
+         .find(|&file| pos.is_piece_at(Piece::new(c, Rook), Square::new(file, back_rank(c))))
+}
+
+// Parses a character of the castling field, which is either one of KQkq,
+// or the file of the rook as in Shredder-FEN and X-FEN, in uppercase for white.
+fn parse_castle_char(pos: &Position, ch: char) -> Result<(Side, Color, File), ParsePosError> {
+    let c = if ch.is_uppercase() { White } else { Black };
+    let king = pos.king_square(c);
+    if king.rank() != back_rank(c) {
+        return Err(ParsePosError::ImpossibleCastling);
+    }
+    let (side, rook_file) = match ch.to_ascii_lowercase() {
+        'k' => (Kingside, outermost_rook_file(pos, Kingside, c)),
+        'q' => (Queenside, outermost_rook_file(pos, Queenside, c)),
+        file_ch @ 'a' ... 'h' => {
+            let file = File((file_ch as u8 - b'a') as i32);
+            let side = if file.0 > king.file().0 { Kingside } else { Queenside };
+            if file == king.file() ||
+               !pos.is_piece_at(Piece::new(c, Rook), Square::new(file, back_rank(c))) {
+                return Err(ParsePosError::ImpossibleCastling);
             }
-            _ => pos_str.push(ch)
-        }
+            (side, Some(file))
+        },
+        _ => return Err(ParsePosError::InvalidCastling),
+    };
+    match rook_file {
+        Some(file) => Ok((side, c, file)),
+        None => Err(ParsePosError::ImpossibleCastling),
     }
+}
+
+pub fn fen_to_position(fen: &str) -> Result<Position, ParsePosError> {
+    let fields: Vec<&str> = fen.split_whitespace().collect();
+    if fields.len() != 6 {
+        return Err(ParsePosError::WrongNumberOfFields);
+    };
     let mut pos = Position::new();
-    let (mut rank, mut file) = (7, 0);
-    let mut decode = HashMap::new();
-    decode.insert('P', WP);
-    decode.insert('K', WK);
-    decode.insert('Q', WQ);
-    decode.insert('B', WB);
-    decode.insert('N', WN);
-    decode.insert('R', WR);
-    decode.insert('p', BP);
-    decode.insert('k', BK);
-    decode.insert('q', BQ);
-    decode.insert('b', BB);
-    decode.insert('n', BN);
-    decode.insert('r', BR);
-    for ch in pos_str.chars() {
-        if ch == '/' {
-            rank = rank - 1;
-            file = 0;
-        } else if ch == '1' {
-            file = file + 1;
-        } else {
-            match decode.get(&ch) {
-                None => return Err(ParsePosError("Unexpected charactor found.")),
-                Some(val) => {
-                    pos.set_at(Square::new(File(file), Rank(rank)), *val);
-                    file = file + 1;
+
+    let ranks: Vec<&str> = fields[0].split('/').collect();
+    if ranks.len() != 8 {
+        return Err(ParsePosError::WrongNumberOfRanks);
+    }
+    for (rank_str, rank) in ranks.iter().zip((0..8).rev()) {
+        let mut file = 0;
+        for ch in rank_str.chars() {
+            if let Some(val) = ch.to_digit(10) {
+                if !(1..=8).contains(&val) {
+                    return Err(ParsePosError::InvalidPiece);
+                }
+                file += val as i32;
+            } else {
+                let piece = try!(piece_from_char(ch).ok_or(ParsePosError::InvalidPiece));
+                if file >= 8 {
+                    return Err(ParsePosError::WrongRankLength);
                 }
+                if piece.piece_type() == Pawn && (rank == 0 || rank == 7) {
+                    return Err(ParsePosError::PawnOnBackRank);
+                }
+                pos.set_at(Square::new(File(file), Rank(rank)), piece);
+                file += 1;
+            }
+            if file > 8 {
+                return Err(ParsePosError::WrongRankLength);
             }
         }
+        if file != 8 {
+            return Err(ParsePosError::WrongRankLength);
+        }
+    }
+    for &c in &[White, Black] {
+        if pos.data.piece_count(Piece::new(c, King)) != 1 {
+            return Err(ParsePosError::WrongNumberOfKings);
+        }
     }
-    let side_to_move = fields[1].chars().next().unwrap();
-    match side_to_move {
-        'w' => pos.set_side_to_move(White),
-        'b' => pos.set_side_to_move(Black),
-        _ => return Err(ParsePosError("Invalid side to move.")),
+
+    match fields[1] {
+        "w" => pos.set_side_to_move(White),
+        "b" => pos.set_side_to_move(Black),
+        _ => return Err(ParsePosError::InvalidSideToMove),
     };
-    let castle = fields[2];
-    for ch in castle.chars() {
-        match ch {
-            'K' => pos.set_castle(Kingside , White, true),
-            'Q' => pos.set_castle(Queenside, White, true),
-            'k' => pos.set_castle(Kingside , Black, true),
-            'q' => pos.set_castle(Queenside, Black, true),
-            _ => {}
+
+    if fields[2] != "-" {
+        for ch in fields[2].chars() {
+            let (side, c, rook_file) = try!(parse_castle_char(&pos, ch));
+            if pos.can_castle(side, c) {
+                return Err(ParsePosError::InvalidCastling);
+            }
+            // Castling rights that are impossible in standard chess are from Chess960.
+            let standard_rook_file = match side {
+                Kingside => File(7),
+                Queenside => File(0),
+            };
+            if pos.king_square(c).file() != File(4) || rook_file != standard_rook_file {
+                pos.set_chess960(true);
+            }
+            pos.set_castle(side, c, Some(rook_file));
         }
     }
-    let en_passant_char = fields[3].chars().next().unwrap();
-    if en_passant_char != '-' {
-        pos.set_en_passant(Some(File((en_passant_char as u8 - b'a') as i32)));
+
+    if fields[3] != "-" {
+        let ep_square: Square = try!(fields[3].parse()
+                                              .map_err(|_| ParsePosError::InvalidEnPassant));
+        // The square passed over, the square the pawn went to,
+        // and the square the pawn came from.
+        let (ep_rank, pawn_rank, from_rank) = match pos.side_to_move() {
+            White => (Rank(5), Rank(4), Rank(6)),
+            Black => (Rank(2), Rank(3), Rank(1)),
+        };
+        if ep_square.rank() != ep_rank {
+            return Err(ParsePosError::InvalidEnPassant);
+        }
+        let file = ep_square.file();
+        let pushed_pawn = Piece::new(pos.side_to_move().invert(), Pawn);
+        if !pos.is_piece_at(pushed_pawn, Square::new(file, pawn_rank)) ||
+           !pos.is_empty_at(ep_square) ||
+           !pos.is_empty_at(Square::new(file, from_rank)) {
+            return Err(ParsePosError::ImpossibleEnPassant);
+        }
+        pos.set_en_passant(Some(file));
     }
+
     match fields[4].parse::<u32>() {
         Ok(val) => pos.set_ply_count(NumPlies(val)),
-        Err(_) => return Err(ParsePosError("Invalid number of plies.")),
+        Err(_) => return Err(ParsePosError::InvalidPlyCount),
+    }
+    match fields[5].parse::<u32>() {
+        Ok(val) if val >= 1 => pos.set_move_count(NumMoves(val)),
+        _ => return Err(ParsePosError::InvalidMoveCount),
+    }
+
+    if pos.can_take_king() {
+        return Err(ParsePosError::OpponentInCheck);
     }
     Ok(pos)
 }
+
+pub fn position_to_fen(pos: &Position) -> String {
+    let mut ans = String::new();
+    for rank in (0..8).rev() {
+        let mut num_empty = 0;
+        for file in 0..8 {
+            match pos.at(Square::new(File(file), Rank(rank))) {
+                None => num_empty += 1,
+                Some(piece) => {
+                    if num_empty != 0 {
+                        ans.push_str(&num_empty.to_string());
+                        num_empty = 0;
+                    }
+                    ans.push(piece_to_char(piece));
+                }
+            }
+        }
+        if num_empty != 0 {
+            ans.push_str(&num_empty.to_string());
+        }
+        if rank != 0 {
+            ans.push('/');
+        }
+    }
+
+    ans.push_str(match pos.side_to_move() {
+        White => " w ",
+        Black => " b ",
+    });
+
+    let mut castle_str = String::new();
+    for &c in &[White, Black] {
+        for &side in &[Kingside, Queenside] {
+            // As in X-FEN, the file of the rook is only given
+            // when it is not the outermost one.
+            match pos.extra_data.castling.rook_file(side, c) {
+                None => {},
+                Some(file) if Some(file) == outermost_rook_file(pos, side, c) => {
+                    castle_str.push(castle_char(side, c));
+                },
+                Some(file) => {
+                    let file_ch = (b'a' + file.0 as u8) as char;
+                    castle_str.push(if c == White { file_ch.to_ascii_uppercase() } else { file_ch });
+                },
+            }
+        }
+    }
+    if castle_str.is_empty() {
+        castle_str.push('-');
+    }
+    ans.push_str(&castle_str);
+
+    match pos.en_passant() {
+        None => ans.push_str(" -"),
+        Some(file) => {
+            let rank = match pos.side_to_move() {
+                White => Rank(5),
+                Black => Rank(2),
+            };
+            ans.push_str(&format!(" {}", Square::new(file, rank)));
+        }
+    }
+
+    ans.push_str(&format!(" {} {}", pos.ply_count().0, pos.move_count().0));
+    ans
+}
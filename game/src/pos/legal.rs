@@ -1,57 +1,389 @@
+//! Generates the legal moves. The checks and pins of the position are found first,
+//! so that the legality of a move can be decided without making it.
+
 use std::iter;
 
-use square::Square;
+use color::{Color, White, Black};
+use square::{Square, File, Rank};
 use moves::Move;
+use move_list::{self, MoveList};
+use piece::{Piece, Pawn, King, Queen, Bishop, Knight, Rook};
+use castle::{Side, Kingside, Queenside};
 
 use super::Position;
+use super::psudo_legal;
+use super::bitboard::BitBoard;
+use super::attacks::{slider_attacks, fixed_attacks, pawn_attacks, attackers_to, between, line};
 
-pub struct Iter<'a>(iter::Chain<NoisyIter<'a>, QuietIter<'a>>);
-impl<'a> Iterator for Iter<'a> {
+pub struct Iter(iter::Chain<NoisyIter, QuietIter>);
+impl Iterator for Iter {
     type Item = Move;
     fn next(&mut self) -> Option<Move> { self.0.next() }
     fn size_hint(&self) -> (usize, Option<usize>) { self.0.size_hint() }
 }
 
-pub struct NoisyIter<'a>(Box<Iterator<Item = Move> + 'a>);
-impl<'a> Iterator for NoisyIter<'a> {
+pub struct NoisyIter(move_list::IntoIter);
+impl Iterator for NoisyIter {
     type Item = Move;
     fn next(&mut self) -> Option<Move> { self.0.next() }
     fn size_hint(&self) -> (usize, Option<usize>) { self.0.size_hint() }
 }
 
-pub struct QuietIter<'a>(Box<Iterator<Item = Move> + 'a>);
-impl<'a> Iterator for QuietIter<'a> {
+pub struct QuietIter(move_list::IntoIter);
+impl Iterator for QuietIter {
     type Item = Move;
     fn next(&mut self) -> Option<Move> { self.0.next() }
     fn size_hint(&self) -> (usize, Option<usize>) { self.0.size_hint() }
 }
 
-pub fn iter<'a>(p: &'a Position) -> Iter<'a> {
+pub fn iter(p: &Position) -> Iter {
     Iter(p.legal_noisy_iter().chain(p.legal_quiet_iter()))
 }
 
-pub fn noisy_iter<'a>(p: &'a Position) -> NoisyIter<'a> {
-    let mut temp = p.clone();
-    NoisyIter(Box::new(p.psudo_legal_noisy_iter().filter(move |x| is_legal(&mut temp, x))))
+pub fn noisy_iter(p: &Position) -> NoisyIter {
+    NoisyIter(list_iter(p, Kind::Noisy))
+}
+
+pub fn quiet_iter(p: &Position) -> QuietIter {
+    QuietIter(list_iter(p, Kind::Quiet))
+}
+
+fn list_iter(p: &Position, kind: Kind) -> move_list::IntoIter {
+    let mut list = MoveList::new();
+    generate(p, &mut list, kind);
+    list.into_iter()
+}
+
+/// Which moves to generate.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum Kind {
+    // Captures, en passant and promotions.
+    Noisy,
+    // The other moves.
+    Quiet,
+    All,
+    // All the moves, when in check.
+    Evasions,
+    // The quiet moves that give check.
+    QuietChecks,
+}
+
+pub fn generate(p: &Position, list: &mut MoveList, kind: Kind) {
+    let info = CheckInfo::new(p);
+    match kind {
+        Kind::Evasions => debug_assert!(info.checkers.count() != 0),
+        Kind::QuietChecks => {
+            let mut quiets = MoveList::new();
+            generate_with_info(p, &info, &mut quiets, Kind::Quiet);
+            for m in quiets.iter().filter(|m| gives_check(p, m)) {
+                list.push(*m);
+            }
+            return;
+        },
+        _ => {},
+    }
+    generate_with_info(p, &info, list, kind);
+}
+
+fn generate_with_info(p: &Position, info: &CheckInfo, list: &mut MoveList, kind: Kind) {
+    let us = p.side_to_move();
+    let empty = p.data.empty_data();
+    let theirs = p.data.color_data(us.invert());
+    let to_mask = match kind {
+        Kind::Noisy => theirs,
+        Kind::Quiet => empty,
+        _ => empty.union(theirs),
+    };
+    let gen_noisy = kind != Kind::Quiet;
+    let gen_quiet = kind != Kind::Noisy;
+
+    generate_king(p, info, list, to_mask);
+    if gen_quiet && info.checkers.count() == 0 {
+        for &side in &[Kingside, Queenside] {
+            if p.can_castle_now(side, us) && castle_is_safe(p, side) {
+                list.push(psudo_legal::castle_move(p, side));
+            }
+        }
+    }
+    // Only the king can get out of a double check.
+    if info.checkers.count() > 1 {
+        return;
+    }
+
+    for &piece_type in &[Knight, Bishop, Rook, Queen] {
+        for from in p.data.piece_data(Piece::new(us, piece_type)).iter() {
+            let reachable = match piece_type {
+                Knight => fixed_attacks(Knight, from),
+                _ => slider_attacks(piece_type, from, info.occupied),
+            };
+            let to_bits = reachable.intersect(to_mask).intersect(info.allowed(from));
+            push_all(p, list, from, to_bits);
+        }
+    }
+
+    generate_pawns(p, info, list, gen_noisy, gen_quiet);
+    if gen_noisy {
+        generate_en_passant(p, info, list);
+    }
+}
+
+/// Whether the pseudo-legal move, for example from Position::unpack_move(), is legal.
+pub fn is_legal(p: &Position, m: &Move) -> bool {
+    let info = CheckInfo::new(p);
+    let from = m.from();
+    if from == info.king {
+        match m.castle() {
+            Some(side) => info.checkers.count() == 0 && castle_is_safe(p, side),
+            None => king_can_go_to(p, &info, m.to()),
+        }
+    } else if m.is_en_passant() {
+        en_passant_is_legal(p, &info, from, m.to())
+    } else {
+        info.checkers.count() <= 1 && info.allowed(from).at(m.to())
+    }
+}
+
+/// Whether the legal move gives check.
+pub fn gives_check(p: &Position, m: &Move) -> bool {
+    let us = p.side_to_move();
+    let king = p.king_square(us.invert());
+    let moved_type = m.promote().unwrap_or(p.moved_piece(m).piece_type());
+
+    // The squares that are not empty and our pieces that stay in place, after the move.
+    let mut occupied = p.occupied();
+    let mut staying = p.data.color_data(us);
+    occupied.remove_at(m.from());
+    staying.remove_at(m.from());
+    let mut moved = [Some((moved_type, m.king_to())), None];
+    if let Some(side) = m.castle() {
+        // The king takes its own rook.
+        occupied.remove_at(m.to());
+        staying.remove_at(m.to());
+        moved[1] = Some((Rook, Square::new(side.rook_to_file(), m.from().rank())));
+    }
+    if m.is_en_passant() {
+        occupied.remove_at(Square::new(m.to().file(), m.from().rank()));
+    }
+    for &(_, s) in moved.iter().flatten() {
+        if !occupied.at(s) {
+            occupied.set_at(s);
+        }
+    }
+
+    // Checks by the moved pieces.
+    let is_direct = moved.iter().flatten().any(|&(piece_type, s)| {
+        let attacks = match piece_type {
+            Pawn => pawn_attacks(us, s),
+            Knight | King => fixed_attacks(piece_type, s),
+            _ => slider_attacks(piece_type, s, occupied),
+        };
+        attacks.at(king)
+    });
+    // Checks by sliders that the move uncovers.
+    let pieces = |piece_type| p.data.piece_data(Piece::new(us, piece_type)).intersect(staying);
+    let is_discovered = slider_attacks(Rook, king, occupied)
+                        .intersect(pieces(Rook).union(pieces(Queen)))
+                        .union(slider_attacks(Bishop, king, occupied)
+                               .intersect(pieces(Bishop).union(pieces(Queen))))
+                        .count() != 0;
+    is_direct || is_discovered
+}
+
+// What is known about the checks and pins against the side to move.
+struct CheckInfo {
+    us: Color,
+    king: Square,
+    occupied: BitBoard,
+    // The pieces giving check.
+    checkers: BitBoard,
+    // Our pieces that are the only ones between our king and an enemy slider.
+    pinned: BitBoard,
+    // Where pieces other than the king can go: to block the check
+    // or to capture the checking piece when in check, and anywhere otherwise.
+    targets: BitBoard,
 }
+impl CheckInfo {
+    fn new(p: &Position) -> CheckInfo {
+        let us = p.side_to_move();
+        let them = us.invert();
+        let king = p.king_square(us);
+        let occupied = p.occupied();
+        let checkers = attackers_to(p, king, occupied).intersect(p.data.color_data(them));
 
-pub fn quiet_iter<'a>(p: &'a Position) -> QuietIter<'a> {
-    let mut temp = p.clone();
-    QuietIter(Box::new(p.psudo_legal_quiet_iter().filter(move |x| is_legal(&mut temp, x))))
+        let pieces = |piece_type| p.data.piece_data(Piece::new(them, piece_type));
+        let empty_board = BitBoard::new();
+        let snipers = slider_attacks(Rook, king, empty_board)
+                      .intersect(pieces(Rook).union(pieces(Queen)))
+                      .union(slider_attacks(Bishop, king, empty_board)
+                             .intersect(pieces(Bishop).union(pieces(Queen))));
+        let mut pinned = BitBoard::new();
+        for sniper in snipers.iter() {
+            let blockers = between(king, sniper).intersect(occupied);
+            if blockers.count() == 1 {
+                pinned = pinned.union(blockers.intersect(p.data.color_data(us)));
+            }
+        }
+
+        let targets = match checkers.iter().next() {
+            None => BitBoard::new_full(),
+            Some(checker) => between(king, checker).union(checkers),
+        };
+        CheckInfo {
+            us: us,
+            king: king,
+            occupied: occupied,
+            checkers: checkers,
+            pinned: pinned,
+            targets: targets,
+        }
+    }
+
+    // Where our piece on `from`, other than the king, can go without leaving the king in check.
+    fn allowed(&self, from: Square) -> BitBoard {
+        if self.pinned.at(from) {
+            self.targets.intersect(line(self.king, from))
+        } else {
+            self.targets
+        }
+    }
+}
+
+fn push_all(p: &Position, list: &mut MoveList, from: Square, to_bits: BitBoard) {
+    for to in to_bits.iter() {
+        push_move(p, list, from, to);
+    }
+}
+
+fn push_move(p: &Position, list: &mut MoveList, from: Square, to: Square) {
+    let mut curr_move = Move::new(from, to);
+    curr_move.set_capture_normal(p.at(to));
+    list.push(curr_move);
+}
+
+fn king_can_go_to(p: &Position, info: &CheckInfo, to: Square) -> bool {
+    // The king must not stay on a line that it is checked along.
+    let mut occupied = info.occupied;
+    occupied.remove_at(info.king);
+    attackers_to(p, to, occupied).intersect(p.data.color_data(info.us.invert())).count() == 0
+}
+
+fn generate_king(p: &Position, info: &CheckInfo, list: &mut MoveList, to_mask: BitBoard) {
+    for to in fixed_attacks(King, info.king).intersect(to_mask).iter() {
+        if king_can_go_to(p, info, to) {
+            push_move(p, list, info.king, to);
+        }
+    }
+}
+
+// Whether the king passes through or lands on an attacked square.
+// Whether it is in check is tested separately.
+fn castle_is_safe(p: &Position, side: Side) -> bool {
+    let us = p.side_to_move();
+    let them = us.invert();
+    let king = p.king_square(us);
+    let rook_from = p.castle_rook_square(side, us).unwrap();
+    let king_to = Square::new(side.king_to_file(), king.rank());
+    let rook_to = Square::new(side.rook_to_file(), king.rank());
+    let theirs = p.data.color_data(them);
+    let passes_attack = side.require_no_attack(us, king.file()).iter().any(|s| {
+        *s != king && attackers_to(p, *s, p.occupied()).intersect(theirs).count() != 0
+    });
+    // In Chess960 the rook may have been shielding the square the king lands on,
+    // for example from a queen on a1 when the rook castles from b1.
+    let mut occupied = p.occupied();
+    occupied.remove_at(king);
+    occupied.remove_at(rook_from);
+    occupied.set_at(king_to);
+    occupied.set_at(rook_to);
+    !passes_attack && attackers_to(p, king_to, occupied).intersect(theirs).count() == 0
 }
 
-fn is_legal(p: &mut Position, curr_move: &Move) -> bool {
-    let c = p.side_to_move();
-    p.with_move(curr_move, |new_pos| {
-        match curr_move.castle() {
-            None => {
-                !new_pos.can_take_king()
-            },
-            Some(side) => {
-                // Check for castling out of check, through check, and into check.
-                let check_squares: Vec<Square> = side.require_no_attack(c);
-                check_squares.iter().all( |val| !new_pos.can_move_to(*val) )
+fn generate_pawns(p: &Position, info: &CheckInfo, list: &mut MoveList,
+                  gen_noisy: bool, gen_quiet: bool) {
+    let us = info.us;
+    // Pawns on promote_rank promote with their next move.
+    let (dy, promote_rank, double_rank) = match us {
+        White => ( 1, Rank(6), Rank(1)),
+        Black => (-1, Rank(1), Rank(6)),
+    };
+    let theirs = p.data.color_data(us.invert());
+    for from in p.data.piece_data(Piece::new(us, Pawn)).iter() {
+        let allowed = info.allowed(from);
+        let is_promotion = from.rank() == promote_rank;
+
+        let (File(f), Rank(r)) = from.to_tuple();
+        let to = Square::from_i32(f, r + dy).unwrap();
+        if p.is_empty_at(to) {
+            if is_promotion {
+                if gen_noisy && allowed.at(to) {
+                    push_promotions(p, list, from, to);
+                }
+            } else if gen_quiet {
+                if allowed.at(to) {
+                    list.push(Move::new(from, to));
+                }
+                if from.rank() == double_rank {
+                    let to2 = Square::from_i32(f, r + 2 * dy).unwrap();
+                    if p.is_empty_at(to2) && allowed.at(to2) {
+                        let mut curr_move = Move::new(from, to2);
+                        curr_move.set_pawn_double_move(true);
+                        list.push(curr_move);
+                    }
+                }
+            }
+        }
+
+        if gen_noisy {
+            for to in pawn_attacks(us, from).intersect(theirs).intersect(allowed).iter() {
+                if is_promotion {
+                    push_promotions(p, list, from, to);
+                } else {
+                    push_move(p, list, from, to);
+                }
             }
         }
-    })
+    }
+}
+
+fn push_promotions(p: &Position, list: &mut MoveList, from: Square, to: Square) {
+    for &new_piece in &[Queen, Knight, Rook, Bishop] {
+        let mut curr_move = Move::new(from, to);
+        curr_move.set_capture_normal(p.at(to));
+        curr_move.set_promote(Some(new_piece));
+        list.push(curr_move);
+    }
+}
+
+fn generate_en_passant(p: &Position, info: &CheckInfo, list: &mut MoveList) {
+    let to_file = match p.en_passant() {
+        Some(f) => f,
+        None => return,
+    };
+    let to_rank = match info.us {
+        White => Rank(5),
+        Black => Rank(2),
+    };
+    let to = Square::new(to_file, to_rank);
+    // Our pawns that attack `to` are where a pawn of the other side on `to` would attack.
+    let froms = pawn_attacks(info.us.invert(), to)
+                .intersect(p.data.piece_data(Piece::new(info.us, Pawn)));
+    for from in froms.iter() {
+        if en_passant_is_legal(p, info, from, to) {
+            let mut curr_move = Move::new(from, to);
+            curr_move.set_en_passant(true);
+            list.push(curr_move);
+        }
+    }
+}
+
+// Two pawns leave the rank of the king at once, so this is tested by
+// finding the attackers of the king after the move.
+fn en_passant_is_legal(p: &Position, info: &CheckInfo, from: Square, to: Square) -> bool {
+    let captured = Square::new(to.file(), from.rank());
+    let mut occupied = info.occupied;
+    occupied.remove_at(from);
+    occupied.remove_at(captured);
+    occupied.set_at(to);
+    attackers_to(p, info.king, occupied).intersect(p.data.color_data(info.us.invert()))
+                                        .count() == 0
 }
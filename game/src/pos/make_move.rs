@@ -1,8 +1,8 @@
 use color::{White, Black};
-use piece::Piece::{self, WK, WR, BK, BR};
-use piece::Pawn;
-use square::{Square, File, Rank};
-use moves::{Move, NumPlies};
+use piece::Piece;
+use piece::{Pawn, King, Rook};
+use square::Square;
+use moves::{Move, NumPlies, NumMoves};
 use castle::{Kingside, Queenside};
 
 use super::{Position, ExtraData};
@@ -30,21 +30,14 @@ pub fn make_move(p: &mut Position, m: &Move) {
         //no en passant
         p.set_en_passant(None);
 
-        let (rook_from, rook_to) = match (curr_color, castle_side) {
-            (White, Kingside ) => (Square::new(File(7), Rank(0)),
-                                   Square::new(File(5), Rank(0))),
-            (White, Queenside) => (Square::new(File(0), Rank(0)),
-                                   Square::new(File(3), Rank(0))),
-            (Black, Kingside ) => (Square::new(File(7), Rank(7)),
-                                   Square::new(File(5), Rank(7))),
-            (Black, Queenside) => (Square::new(File(0), Rank(7)),
-                                   Square::new(File(3), Rank(7))),
-        };
+        // The king takes its own rook. In Chess960 the king or the rook may
+        // land where the other one started, so both are removed first.
+        let curr_rook = Piece::new(curr_color, Rook);
+        let rook_to = Square::new(castle_side.rook_to_file(), from.rank());
 
         p.remove_at(from, curr_piece);
-        p.set_at(to, curr_piece);
-        let curr_rook = p.at(rook_from).unwrap();
-        p.remove_at(rook_from, curr_rook);
+        p.remove_at(to, curr_rook);
+        p.set_at(m.king_to(), curr_piece);
         p.set_at(rook_to, curr_rook);
 
     } else if m.is_en_passant() {
@@ -86,25 +79,23 @@ pub fn make_move(p: &mut Position, m: &Move) {
     }
 
     //castling
-    if p.at(Square::new(File(4), Rank(0))) != Some(WK) {
-        p.set_castle(Queenside, White, false);
-        p.set_castle(Kingside , White, false);
-    }
-    if p.at(Square::new(File(0), Rank(0))) != Some(WR) {
-        p.set_castle(Queenside, White, false);
-    }
-    if p.at(Square::new(File(7), Rank(0))) != Some(WR) {
-        p.set_castle(Kingside , White, false);
+    if curr_piece.piece_type() == King {
+        p.set_castle(Queenside, curr_color, None);
+        p.set_castle(Kingside , curr_color, None);
     }
-    if p.at(Square::new(File(4), Rank(7))) != Some(BK) {
-        p.set_castle(Queenside, Black, false);
-        p.set_castle(Kingside , Black, false);
-    }
-    if p.at(Square::new(File(0), Rank(7))) != Some(BR) {
-        p.set_castle(Queenside, Black, false);
+    // A rook that moves or is captured cannot castle any more.
+    for &c in &[White, Black] {
+        for &side in &[Kingside, Queenside] {
+            let rook_square = p.castle_rook_square(side, c);
+            if rook_square == Some(from) || rook_square == Some(to) {
+                p.set_castle(side, c, None);
+            }
+        }
     }
-    if p.at(Square::new(File(7), Rank(7))) != Some(BR) {
-        p.set_castle(Kingside , Black, false);
+
+    if curr_color == Black {
+        let NumMoves(temp) = p.move_count();
+        p.set_move_count(NumMoves(temp+1));
     }
 
     p.swap_side_to_move();
@@ -121,30 +112,23 @@ pub fn unmake_move(p: &mut Position, m: &Move, extra_data: ExtraData) {
 
     let from = m.from();
     let to = m.to();
-    let curr_piece = p.at(to).unwrap();
     let curr_color = p.side_to_move().invert();
 
     if let Some(castle_side) = m.castle() {
 
-        let (rook_from, rook_to) = match (curr_color, castle_side) {
-            (White, Kingside ) => (Square::new(File(7), Rank(0)),
-                                   Square::new(File(5), Rank(0))),
-            (White, Queenside) => (Square::new(File(0), Rank(0)),
-                                   Square::new(File(3), Rank(0))),
-            (Black, Kingside ) => (Square::new(File(7), Rank(7)),
-                                   Square::new(File(5), Rank(7))),
-            (Black, Queenside) => (Square::new(File(0), Rank(7)),
-                                   Square::new(File(3), Rank(7))),
-        };
+        let curr_king = Piece::new(curr_color, King);
+        let curr_rook = Piece::new(curr_color, Rook);
+        let rook_to = Square::new(castle_side.rook_to_file(), from.rank());
 
-        p.remove_at(to, curr_piece);
-        p.set_at(from, curr_piece);
-        let curr_rook = p.at(rook_to).unwrap();
+        p.remove_at(m.king_to(), curr_king);
         p.remove_at(rook_to, curr_rook);
-        p.set_at(rook_from, curr_rook);
+        p.set_at(from, curr_king);
+        p.set_at(to, curr_rook);
 
     } else if m.is_en_passant() {
 
+        let curr_piece = p.at(to).unwrap();
+
         let captured = Square::new(to.file(), from.rank());
         let captured_piece = Piece::new(curr_color.invert(), Pawn);
 
@@ -162,6 +146,7 @@ pub fn unmake_move(p: &mut Position, m: &Move, extra_data: ExtraData) {
 
     } else {
 
+        let curr_piece = p.at(to).unwrap();
         p.remove_at(to, curr_piece);
         p.set_at(from, curr_piece);
         if let Some(captured_piece) = m.capture_normal() {
@@ -1,13 +1,12 @@
+use move_list::MoveList;
+
 use super::Position;
 
 pub fn is_checkmated(p: &mut Position) -> bool {
     if p.has_legal_moves() {
         false
     } else {
-        p.swap_side_to_move();
-        let ans = p.can_take_king();
-        p.swap_side_to_move();
-        ans
+        p.is_in_check()
     }
 }
 
@@ -15,13 +14,12 @@ pub fn is_stalemated(p: &mut Position) -> bool {
     if p.has_legal_moves() {
         false
     } else {
-        p.swap_side_to_move();
-        let ans = !p.can_take_king();
-        p.swap_side_to_move();
-        ans
+        !p.is_in_check()
     }
 }
 
 pub fn has_legal_moves(p: &Position) -> bool {
-    p.legal_iter().next().is_some()
+    let mut moves = MoveList::new();
+    p.generate_legal(&mut moves);
+    !moves.is_empty()
 }
@@ -1,28 +1,41 @@
 //! Implements the game representation
 
 use std::str::FromStr;
+use std::fmt;
 
 pub use self::eval::{Score, ScoreUnit};
+pub use self::eval_params::{EvalParams, PhaseScore};
 pub use self::hash::ZobristHash;
+pub use self::bitboard::BitBoard;
+pub use self::fen::ParsePosError;
+pub use self::san::ParseSanError;
 
-use super::piece::Piece;
+use super::piece::{Piece, Pawn, Queen, Rook, Bishop, Knight};
 use super::color::Color;
 use super::square::{File, Square};
-use super::castle::{CastlingData, Side};
-use super::moves::{Move, NumPlies};
+use super::castle::{self, CastlingData, Side};
+use super::moves::{Move, PackedMove, NumPlies, NumMoves};
+use super::move_list::MoveList;
 
 use self::board::Board;
-use self::fen::{fen_to_position, ParsePosError};
+use self::fen::{fen_to_position, position_to_fen};
 
 mod board;
 mod bitboard;
+mod attacks;
 mod legal;
 mod psudo_legal;
 mod make_move;
 mod mate;
+mod draw;
 mod fen;
+mod san;
 mod eval;
+mod eval_params;
+mod pawns;
+mod see;
 mod hash;
+mod perft;
 
 /// Data required to unmake moves
 #[derive(PartialEq, Eq, Clone, Debug)]
@@ -30,6 +43,7 @@ pub struct ExtraData {
     castling: CastlingData,
     en_passant: Option<File>,
     ply_count: NumPlies,
+    move_count: NumMoves,
 }
 
 /// A complete representation of a chess position.
@@ -39,6 +53,14 @@ pub struct Position {
     side_to_move: Color,
     extra_data: ExtraData,
     hash: ZobristHash,
+    // Only includes the pawns, for the pawn structure cache of the evaluation.
+    pawn_hash: ZobristHash,
+    // Kept up to date by set_at() and remove_at(), so that eval() does not need to
+    // go through all the pieces.
+    piece_score: PhaseScore,
+    phase: i32,
+    // Whether castling moves are written as the king taking its own rook.
+    chess960: bool,
 }
 impl Position {
     fn new() -> Position {
@@ -49,8 +71,13 @@ impl Position {
                 castling: CastlingData::new(),
                 en_passant: None,
                 ply_count: NumPlies(0),
+                move_count: NumMoves(1),
             },
             hash: ZobristHash(0),
+            pawn_hash: ZobristHash(0),
+            piece_score: PhaseScore::zero(),
+            phase: 0,
+            chess960: false,
         }
     }
     pub fn start() -> Self {
@@ -73,10 +100,20 @@ impl Position {
     fn set_at(&mut self, s: Square, p: Piece) {
         self.data.set_at(s, p);
         self.hash = self.hash ^ hash::piece_square(p, s);
+        if p.piece_type() == Pawn {
+            self.pawn_hash = self.pawn_hash ^ hash::piece_square(p, s);
+        }
+        self.piece_score = self.piece_score + eval::piece_square_value(p, s);
+        self.phase += eval_params::phase_of_piece_type(p.piece_type());
     }
     fn remove_at(&mut self, s: Square, p: Piece) {
         self.data.remove_at(s, p);
         self.hash = self.hash ^ hash::piece_square(p, s);
+        if p.piece_type() == Pawn {
+            self.pawn_hash = self.pawn_hash ^ hash::piece_square(p, s);
+        }
+        self.piece_score = self.piece_score - eval::piece_square_value(p, s);
+        self.phase -= eval_params::phase_of_piece_type(p.piece_type());
     }
 
     fn king_square(&self, c: Color) -> Square {
@@ -103,19 +140,39 @@ impl Position {
     fn can_castle(&self, side: Side, c: Color) -> bool {
         self.extra_data.castling.get(side, c)
     }
+    // Where the rook that castles starts, if the side can still castle.
+    fn castle_rook_square(&self, side: Side, c: Color) -> Option<Square> {
+        self.extra_data.castling.rook_file(side, c)
+            .map(|file| Square::new(file, castle::back_rank(c)))
+    }
     // Does not check for castling out of check, through check, or into check.
     fn can_castle_now(&self, side: Side, c: Color) -> bool {
-        self.can_castle(side, c) &&
-            side.require_empty_squares(c).iter().all( |x| self.is_empty_at(*x) )
+        match self.castle_rook_square(side, c) {
+            None => false,
+            Some(rook_square) => {
+                let king_file = self.king_square(c).file();
+                side.require_empty_squares(c, king_file, rook_square.file())
+                    .iter().all( |x| self.is_empty_at(*x) )
+            }
+        }
     }
-    fn set_castle(&mut self, side:Side, c:Color, val: bool) {
+    fn set_castle(&mut self, side:Side, c:Color, val: Option<File>) {
         let old_val = self.extra_data.castling.get(side, c);
-        if val != old_val {
-            self.extra_data.castling.set(side, c, val);
+        self.extra_data.castling.set(side, c, val);
+        if val.is_some() != old_val {
             self.hash = self.hash ^ hash::castling(side, c);
         }
     }
 
+    /// Whether castling moves are read and written as the king taking its own rook,
+    /// as in Chess960, instead of as the king moving two squares.
+    pub fn is_chess960(&self) -> bool {
+        self.chess960
+    }
+    pub fn set_chess960(&mut self, val: bool) {
+        self.chess960 = val;
+    }
+
     fn en_passant(&self) -> Option<File> {
         self.extra_data.en_passant
     }
@@ -136,47 +193,112 @@ impl Position {
         self.extra_data.ply_count = val;
     }
 
+    /// The fullmove number, which starts at 1 and increases after each move of black.
+    pub fn move_count(&self) -> NumMoves {
+        self.extra_data.move_count
+    }
+    fn set_move_count(&mut self, val: NumMoves) {
+        self.extra_data.move_count = val;
+    }
+
     fn extra_data(&self) -> &ExtraData {
         &self.extra_data
     }
     fn set_extra_data(&mut self, val: ExtraData) {
         for &side in &[Side::Kingside, Side::Queenside] {
             for &color in &[Color::White, Color::Black] {
-                self.set_castle(side, color, val.castling.get(side, color));
+                self.set_castle(side, color, val.castling.rook_file(side, color));
             }
         }
         self.set_en_passant(val.en_passant);
         self.set_ply_count(val.ply_count);
+        self.set_move_count(val.move_count);
     }
 
     pub fn hash(&self) -> ZobristHash { self.hash }
+    pub fn pawn_hash(&self) -> ZobristHash { self.pawn_hash }
+    fn piece_score(&self) -> PhaseScore { self.piece_score }
+    fn phase(&self) -> i32 { self.phase }
 
-    fn psudo_legal_iter<'a>(&'a self) -> psudo_legal::Iter<'a> {
-        psudo_legal::iter(self)
-    }
-    fn psudo_legal_noisy_iter<'a>(&'a self) -> psudo_legal::NoisyIter {
-        psudo_legal::noisy_iter(self)
-    }
-    fn psudo_legal_quiet_iter<'a>(&'a self) -> psudo_legal::QuietIter {
-        psudo_legal::quiet_iter(self)
+    pub fn to_fen(&self) -> String {
+        position_to_fen(self)
     }
 
-    pub fn legal_iter<'a>(&'a self) -> legal::Iter<'a> {
+    pub fn legal_iter(&self) -> legal::Iter {
         legal::iter(self)
     }
-    pub fn legal_noisy_iter<'a>(&'a self) -> legal::NoisyIter<'a> {
+    pub fn legal_noisy_iter(&self) -> legal::NoisyIter {
         legal::noisy_iter(self)
     }
-    pub fn legal_quiet_iter<'a>(&'a self) -> legal::QuietIter<'a> {
+    pub fn legal_quiet_iter(&self) -> legal::QuietIter {
         legal::quiet_iter(self)
     }
 
-    fn can_move_to(&self, to: Square) -> bool {
-        self.psudo_legal_iter().any( |m| m.to() == to )
+    /// Adds all the legal moves to the list.
+    pub fn generate_legal(&self, list: &mut MoveList) {
+        legal::generate(self, list, legal::Kind::All)
+    }
+    /// Adds the legal captures, en passant captures and promotions to the list.
+    pub fn generate_captures(&self, list: &mut MoveList) {
+        legal::generate(self, list, legal::Kind::Noisy)
+    }
+    /// Adds the legal moves that generate_captures() leaves out to the list.
+    pub fn generate_quiets(&self, list: &mut MoveList) {
+        legal::generate(self, list, legal::Kind::Quiet)
+    }
+    /// Adds all the legal moves to the list, when the side to move is in check.
+    pub fn generate_evasions(&self, list: &mut MoveList) {
+        legal::generate(self, list, legal::Kind::Evasions)
+    }
+    /// Adds the legal moves from generate_quiets() that give check to the list.
+    pub fn generate_quiet_checks(&self, list: &mut MoveList) {
+        legal::generate(self, list, legal::Kind::QuietChecks)
+    }
+    /// Whether the legal move checks the king of the other side.
+    pub fn gives_check(&self, m: &Move) -> bool {
+        legal::gives_check(self, m)
+    }
+
+    /// The pseudo-legal move that was packed by Move::pack(), if any.
+    /// This is much cheaper than searching through all legal moves.
+    pub fn unpack_move(&self, m: PackedMove) -> Option<Move> {
+        psudo_legal::unpack_move(self, m)
+    }
+
+    /// Whether the pseudo-legal move, for example from unpack_move(), is legal.
+    pub fn is_legal(&mut self, m: &Move) -> bool {
+        legal::is_legal(self, m)
+    }
+    /// The piece moved by the pseudo-legal move.
+    pub fn moved_piece(&self, m: &Move) -> Piece {
+        self.at(m.from()).expect("moved_piece() of a move from an empty square")
+    }
+
+    /// The squares that are not empty.
+    pub fn occupied(&self) -> BitBoard {
+        !self.data.empty_data()
+    }
+    /// The pieces of both colors among `occupied` that attack s,
+    /// if `occupied` are the squares that are not empty.
+    pub fn attackers_to(&self, s: Square, occupied: BitBoard) -> BitBoard {
+        attacks::attackers_to(self, s, occupied)
+    }
+    fn is_attacked_by(&self, s: Square, c: Color) -> bool {
+        attacks::is_attacked_by(self, s, c)
     }
     fn can_take_king(&self) -> bool {
-        let king_square = self.king_square(self.side_to_move().invert());
-        self.can_move_to(king_square)
+        let c = self.side_to_move();
+        self.is_attacked_by(self.king_square(c.invert()), c)
+    }
+    /// Whether the side to move is in check.
+    pub fn is_in_check(&self) -> bool {
+        self.checkers().count() != 0
+    }
+    /// The pieces that check the king of the side to move.
+    pub fn checkers(&self) -> BitBoard {
+        let c = self.side_to_move();
+        self.attackers_to(self.king_square(c), self.occupied())
+            .intersect(self.data.color_data(c.invert()))
     }
 
     pub fn make_move(&mut self, m: &Move) {
@@ -196,9 +318,51 @@ impl Position {
         mate::has_legal_moves(self)
     }
 
+    pub fn is_draw_by_fifty_move_rule(&mut self) -> bool {
+        draw::is_draw_by_fifty_move_rule(self)
+    }
+    /// Counts how many times this position occurred before.
+    /// `history` is the hashes of all previous positions in the game, oldest first.
+    pub fn repetition_count(&self, history: &[ZobristHash]) -> usize {
+        draw::repetition_count(self, history)
+    }
+    /// Checks for draws by threefold repetition or by the fifty-move rule.
+    /// `history` is the hashes of all previous positions in the game, oldest first.
+    pub fn is_draw_by_rule(&mut self, history: &[ZobristHash]) -> bool {
+        draw::is_draw_by_rule(self, history)
+    }
+
+    /// Counts the leaf nodes of the legal move tree of the given depth.
+    pub fn perft(&mut self, depth: NumPlies) -> u64 {
+        perft::perft(self, depth)
+    }
+    /// Like `perft()`, but gives the count separately for each legal move.
+    pub fn perft_divide(&mut self, depth: NumPlies) -> Vec<(Move, u64)> {
+        perft::perft_divide(self, depth)
+    }
+
+    /// Finds the legal move described by the given SAN string.
+    pub fn parse_san(&self, s: &str) -> Result<Move, ParseSanError> {
+        san::san_to_move(self, s)
+    }
+
+    /// The material won by the move if both sides then keep capturing on its
+    /// destination square with their least valuable piece, while that gains material.
+    /// The move must be pseudo-legal.
+    pub fn see(&self, m: &Move) -> ScoreUnit {
+        see::see(self, m)
+    }
+    /// Whether see(m) is at least threshold.
+    pub fn see_ge(&self, m: &Move, threshold: ScoreUnit) -> bool {
+        see::see_ge(self, m, threshold)
+    }
+
     pub fn eval(&mut self) -> Score {
         eval::eval(self)
     }
+    pub fn eval_with(&mut self, params: &EvalParams) -> Score {
+        eval::eval_with(self, params)
+    }
 
     pub fn with_move<T, F: FnOnce(&mut Position) -> T>(&mut self, curr_move: &Move,f: F) -> T {
         let extra_data = self.extra_data().clone();
@@ -207,6 +371,24 @@ impl Position {
         self.unmake_move(curr_move, extra_data);
         ans
     }
+    /// Like with_move(), but the side to move passes instead, which is not a legal move.
+    /// Must not be used when in check.
+    pub fn with_null_move<T, F: FnOnce(&mut Position) -> T>(&mut self, f: F) -> T {
+        let extra_data = self.extra_data().clone();
+        self.set_en_passant(None);
+        self.swap_side_to_move();
+        let ans = f(self);
+        self.swap_side_to_move();
+        self.set_extra_data(extra_data);
+        ans
+    }
+
+    /// Whether the side has any pieces other than the king and pawns.
+    pub fn has_non_pawn_material(&self, c: Color) -> bool {
+        [Queen, Rook, Bishop, Knight].iter().any(|&x| {
+            self.data.piece_count(Piece::new(c, x)) != 0
+        })
+    }
 }
 
 impl FromStr for Position {
@@ -216,6 +398,12 @@ impl FromStr for Position {
     }
 }
 
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_fen())
+    }
+}
+
 pub fn at_in_pos(pos: &Position, s: Square) -> Option<Piece> {
     pos.at(s)
 }
@@ -223,3 +411,7 @@ pub fn at_in_pos(pos: &Position, s: Square) -> Option<Piece> {
 pub fn is_empty_at_in_pos(pos: &Position, s: Square) -> bool {
     pos.is_empty_at(s)
 }
+
+pub fn move_to_san_in_pos(pos: &Position, m: &Move) -> String {
+    san::move_to_san(pos, m)
+}
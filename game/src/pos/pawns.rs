@@ -0,0 +1,105 @@
+//! Evaluates the pawn structure, and caches the result by the pawn hash.
+
+use color::{Color, White, Black};
+use piece::{Piece, Pawn};
+use square::{Square, File, Rank};
+
+use super::Position;
+use super::bitboard::BitBoard;
+use super::eval_params::{EvalParams, PhaseScore};
+use super::hash::ZobristHash;
+
+// Must be a power of two.
+const PAWN_TABLE_SIZE: usize = 1 << 14;
+
+/// Remembers the pawn structure score of recently seen pawn hashes.
+///
+/// The scores depend on the weights, so a table must only be used with one EvalParams.
+pub struct PawnTable {
+    entries: Vec<Option<(ZobristHash, PhaseScore)>>,
+}
+impl PawnTable {
+    pub fn new() -> PawnTable {
+        PawnTable { entries: vec![None; PAWN_TABLE_SIZE] }
+    }
+
+    pub fn get(&mut self, p: &Position, params: &EvalParams) -> PhaseScore {
+        let hash = p.pawn_hash();
+        let idx = (hash.0 as usize) & (PAWN_TABLE_SIZE - 1);
+        if let Some((key, score)) = self.entries[idx] {
+            if key == hash {
+                return score;
+            }
+        }
+        let score = pawn_structure(p, params);
+        self.entries[idx] = Some((hash, score));
+        score
+    }
+}
+
+/// The pawn structure score from white's point of view.
+pub fn pawn_structure(p: &Position, params: &EvalParams) -> PhaseScore {
+    let white = p.data.piece_data(Piece::new(White, Pawn));
+    let black = p.data.piece_data(Piece::new(Black, Pawn));
+    score_for_color(White, white, black, params) - score_for_color(Black, black, white, params)
+}
+
+fn score_for_color(c: Color, ours: BitBoard, theirs: BitBoard, params: &EvalParams) -> PhaseScore {
+    let forward = match c {
+        White => 1,
+        Black => -1,
+    };
+    let has_pawn = |pawns: BitBoard, f: i32, r: i32| {
+        Square::from_i32(f, r).is_some_and(|s| pawns.at(s))
+    };
+    // Whether there is a pawn on file f, on the same rank as r or further forward.
+    let has_pawn_ahead = |pawns: BitBoard, f: i32, r: i32| {
+        (0..8).any(|i| has_pawn(pawns, f, r + forward * i))
+    };
+    let is_passed = |f: i32, r: i32| {
+        (f-1 .. f+2).all(|x| !has_pawn_ahead(theirs, x, r + forward))
+    };
+
+    let mut ans = PhaseScore::zero();
+    for s in ours.iter() {
+        let (File(f), Rank(r)) = s.to_tuple();
+        let is_defended = has_pawn(ours, f-1, r - forward) || has_pawn(ours, f+1, r - forward);
+
+        if is_passed(f, r) {
+            let relative_rank = match c {
+                White => r,
+                Black => 7 - r,
+            };
+            ans = ans + params.passed[relative_rank as usize];
+            let is_connected = [f-1, f+1].iter().any(|&x| {
+                (r-1 .. r+2).any(|y| has_pawn(ours, x, y) && is_passed(x, y))
+            });
+            if is_connected {
+                ans = ans + params.connected_passer;
+            }
+            if is_defended {
+                ans = ans + params.protected_passer;
+            }
+        }
+
+        if has_pawn_ahead(ours, f, r + forward) {
+            ans = ans + params.doubled;
+        }
+
+        let has_neighbour = (0..8).any(|x| has_pawn(ours, f-1, x) || has_pawn(ours, f+1, x));
+        if !has_neighbour {
+            ans = ans + params.isolated;
+        } else {
+            // No pawn can come to defend it, and it cannot safely move forward.
+            let can_be_supported = [f-1, f+1].iter().any(|&x| {
+                (0..8).any(|i| has_pawn(ours, x, r - forward * i))
+            });
+            let is_stop_attacked = has_pawn(theirs, f-1, r + 2 * forward) ||
+                                   has_pawn(theirs, f+1, r + 2 * forward);
+            if !can_be_supported && is_stop_attacked {
+                ans = ans + params.backward;
+            }
+        }
+    }
+    ans
+}
@@ -0,0 +1,33 @@
+//! Counts the leaf nodes of the legal move tree, for verifying move generation.
+
+use moves::{Move, NumPlies};
+use move_list::MoveList;
+
+use super::Position;
+
+pub fn perft(p: &mut Position, depth: NumPlies) -> u64 {
+    if depth.0 == 0 {
+        return 1;
+    }
+    let mut moves = MoveList::new();
+    p.generate_legal(&mut moves);
+    if depth.0 == 1 {
+        return moves.len() as u64;
+    }
+    let next_depth = NumPlies(depth.0 - 1);
+    moves.iter()
+         .map(|m| p.with_move(m, |new_pos| perft(new_pos, next_depth)))
+         .sum()
+}
+
+pub fn perft_divide(p: &mut Position, depth: NumPlies) -> Vec<(Move, u64)> {
+    assert!(depth.0 >= 1);
+    let next_depth = NumPlies(depth.0 - 1);
+    let moves: Vec<Move> = p.legal_iter().collect();
+    moves.into_iter()
+         .map(|m| {
+             let count = p.with_move(&m, |new_pos| perft(new_pos, next_depth));
+             (m, count)
+         })
+         .collect()
+}
@@ -0,0 +1,183 @@
+//! Standard Algebraic Notation (SAN) for moves.
+
+use std::fmt;
+
+use moves::Move;
+use square::{Square, File, Rank};
+use castle::{Side, Kingside, Queenside};
+use piece::Type::{self, Pawn, King, Queen, Bishop, Knight, Rook};
+
+use super::Position;
+
+/// The reason why a SAN string could not be converted into a legal move.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum ParseSanError {
+    InvalidSyntax,
+    IllegalMove,
+    AmbiguousMove,
+}
+impl fmt::Display for ParseSanError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", match *self {
+            ParseSanError::InvalidSyntax => "invalid SAN syntax",
+            ParseSanError::IllegalMove   => "no legal move matches",
+            ParseSanError::AmbiguousMove => "more than one legal move matches",
+        })
+    }
+}
+
+fn piece_type_to_char(piece_type: Type) -> char {
+    match piece_type {
+        Pawn   => 'P',
+        King   => 'K',
+        Queen  => 'Q',
+        Bishop => 'B',
+        Knight => 'N',
+        Rook   => 'R',
+    }
+}
+
+fn piece_type_from_char(ch: char) -> Option<Type> {
+    Some(match ch {
+        'K' => King,
+        'Q' => Queen,
+        'B' => Bishop,
+        'N' => Knight,
+        'R' => Rook,
+        _ => return None,
+    })
+}
+
+fn moved_piece_type(p: &Position, m: &Move) -> Type {
+    p.at(m.from()).expect("no piece on the from square").piece_type()
+}
+
+/// Converts a legal move into SAN, including the check or checkmate suffix.
+pub fn move_to_san(p: &Position, m: &Move) -> String {
+    let mut ans = match m.castle() {
+        Some(Kingside) => "O-O".to_string(),
+        Some(Queenside) => "O-O-O".to_string(),
+        None => san_without_castle(p, m),
+    };
+    let mut temp = p.clone();
+    temp.make_move(m);
+    if temp.is_checkmated() {
+        ans.push('#');
+    } else if temp.is_in_check() {
+        ans.push('+');
+    }
+    ans
+}
+
+fn san_without_castle(p: &Position, m: &Move) -> String {
+    let mut ans = String::new();
+    let piece_type = moved_piece_type(p, m);
+    let is_capture = m.capture_normal().is_some() || m.is_en_passant();
+
+    if piece_type == Pawn {
+        if is_capture {
+            ans.push_str(&m.from().file().to_string());
+        }
+    } else {
+        ans.push(piece_type_to_char(piece_type));
+
+        let others: Vec<Square> = p.legal_iter()
+                                   .filter(|x| x.to() == m.to() && x.from() != m.from() &&
+                                               moved_piece_type(p, x) == piece_type)
+                                   .map(|x| x.from())
+                                   .collect();
+        if !others.is_empty() {
+            if others.iter().all(|x| x.file() != m.from().file()) {
+                ans.push_str(&m.from().file().to_string());
+            } else if others.iter().all(|x| x.rank() != m.from().rank()) {
+                ans.push_str(&m.from().rank().to_string());
+            } else {
+                ans.push_str(&m.from().to_string());
+            }
+        }
+    }
+
+    if is_capture {
+        ans.push('x');
+    }
+    ans.push_str(&m.to().to_string());
+    if let Some(promote) = m.promote() {
+        ans.push('=');
+        ans.push(piece_type_to_char(promote));
+    }
+    ans
+}
+
+/// Finds the legal move described by the SAN string.
+/// Check and checkmate suffixes and annotations such as "!?" are ignored.
+pub fn san_to_move(p: &Position, s: &str) -> Result<Move, ParseSanError> {
+    let s = s.trim().trim_end_matches(|ch| "+#!?".contains(ch));
+
+    let castle: Option<Side> = match s {
+        "O-O" | "0-0" => Some(Kingside),
+        "O-O-O" | "0-0-0" => Some(Queenside),
+        _ => None,
+    };
+    if let Some(side) = castle {
+        return p.legal_iter()
+                .find(|x| x.castle() == Some(side))
+                .ok_or(ParseSanError::IllegalMove);
+    }
+
+    if !s.is_ascii() || s.len() < 2 {
+        return Err(ParseSanError::InvalidSyntax);
+    }
+    let (piece_type, s) = match s.chars().next().and_then(piece_type_from_char) {
+        Some(val) => (val, &s[1..]),
+        None => (Pawn, s),
+    };
+
+    // Strip the promotion piece, with or without the '='.
+    let (s, promote) = match s.chars().last().and_then(piece_type_from_char) {
+        Some(val) if piece_type == Pawn => {
+            let rest = &s[..s.len()-1];
+            (rest.trim_end_matches('='), Some(val))
+        },
+        _ => (s, None),
+    };
+    if promote == Some(King) {
+        return Err(ParseSanError::InvalidSyntax);
+    }
+
+    if s.len() < 2 {
+        return Err(ParseSanError::InvalidSyntax);
+    }
+    let (prefix, to_str) = s.split_at(s.len() - 2);
+    let to: Square = try!(to_str.parse().map_err(|_| ParseSanError::InvalidSyntax));
+
+    let mut from_file: Option<File> = None;
+    let mut from_rank: Option<Rank> = None;
+    let mut is_capture = false;
+    for ch in prefix.chars() {
+        match ch {
+            'a' ... 'h' if from_file.is_none() && from_rank.is_none() && !is_capture => {
+                from_file = Some(File((ch as u8 - b'a') as i32));
+            },
+            '1' ... '8' if from_rank.is_none() && !is_capture => {
+                from_rank = Some(Rank((ch as u8 - b'1') as i32));
+            },
+            'x' | ':' if !is_capture => is_capture = true,
+            _ => return Err(ParseSanError::InvalidSyntax),
+        }
+    }
+
+    let mut candidates = p.legal_iter().filter(|x| {
+        x.to() == to &&
+            x.castle().is_none() &&
+            x.promote() == promote &&
+            is_capture == (x.capture_normal().is_some() || x.is_en_passant()) &&
+            moved_piece_type(p, x) == piece_type &&
+            from_file.is_none_or(|f| x.from().file() == f) &&
+            from_rank.is_none_or(|r| x.from().rank() == r)
+    });
+    match (candidates.next(), candidates.next()) {
+        (None, _) => Err(ParseSanError::IllegalMove),
+        (Some(val), None) => Ok(val),
+        (Some(_), Some(_)) => Err(ParseSanError::AmbiguousMove),
+    }
+}
@@ -0,0 +1,141 @@
+//! Static exchange evaluation, which finds out how much material a capture wins
+//! if both sides keep recapturing on the same square with their least valuable piece.
+
+use std::cmp::min;
+
+use color::Color;
+use moves::Move;
+use piece::{self, Piece, Pawn, King, Queen, Bishop, Knight, Rook};
+use square::Square;
+
+use super::Position;
+use super::bitboard::BitBoard;
+use super::eval::ScoreUnit;
+use super::attacks::attackers_to;
+
+fn see_value(piece_type: piece::Type) -> i32 {
+    match piece_type {
+        Pawn   =>   100,
+        Knight =>   300,
+        Bishop =>   300,
+        Rook   =>   500,
+        Queen  =>   900,
+        King   => 20000,
+    }
+}
+
+// Least valuable first.
+static ATTACKER_ORDER: [piece::Type; 6] = [Pawn, Knight, Bishop, Rook, Queen, King];
+
+// Makes the move itself, and returns the material it wins, the piece left
+// on the target square, and the occupied squares after it.
+fn first_capture(p: &Position, m: &Move) -> (i32, piece::Type, BitBoard) {
+    let mut occupied = !p.data.empty_data();
+    let mut piece_type = p.at(m.from()).expect("see() of a move from an empty square")
+                          .piece_type();
+    let mut gain = if m.is_en_passant() {
+        occupied.remove_at(Square::new(m.to().file(), m.from().rank()));
+        see_value(Pawn)
+    } else {
+        m.capture_normal().map_or(0, |x| see_value(x.piece_type()))
+    };
+    if let Some(promote) = m.promote() {
+        gain += see_value(promote) - see_value(Pawn);
+        piece_type = promote;
+    }
+    occupied.remove_at(m.from());
+    (gain, piece_type, occupied)
+}
+
+// The least valuable piece of the side that can capture on the square next, if any.
+fn next_attacker(p: &Position, to: Square, occupied: BitBoard,
+                 side: Color) -> Option<(piece::Type, Square)> {
+    let attackers = attackers_to(p, to, occupied).intersect(p.data.color_data(side));
+    let (attacker_type, from) = ATTACKER_ORDER.iter().filter_map(|&x| {
+        let bits = attackers.intersect(p.data.piece_data(Piece::new(side, x)));
+        bits.iter().next().map(|s| (x, s))
+    }).next()?;
+    if attacker_type == King {
+        // The king cannot capture a defended piece.
+        let mut after = occupied;
+        after.remove_at(from);
+        let defenders = attackers_to(p, to, after).intersect(p.data.color_data(side.invert()));
+        if defenders.count() != 0 {
+            return None;
+        }
+    }
+    Some((attacker_type, from))
+}
+
+pub fn see(p: &Position, m: &Move) -> ScoreUnit {
+    if m.castle().is_some() {
+        return ScoreUnit(0);
+    }
+    let to = m.to();
+    let (gain, mut piece_type, mut occupied) = first_capture(p, m);
+    let mut side = p.side_to_move();
+
+    // gains[i] is the material won by the side making the ith capture,
+    // if the exchange stops right after it. Every capture removes one of
+    // at most 32 pieces, so the array is never full.
+    let mut gains = [0; 32];
+    gains[0] = gain;
+    let mut num_captures = 1;
+    loop {
+        side = side.invert();
+        let (attacker_type, from) = match next_attacker(p, to, occupied, side) {
+            Some(val) => val,
+            None => break,
+        };
+        gains[num_captures] = see_value(piece_type) - gains[num_captures - 1];
+        num_captures += 1;
+        piece_type = attacker_type;
+        occupied.remove_at(from);
+    }
+
+    // Each side may stop capturing whenever continuing loses material.
+    for i in (1..num_captures).rev() {
+        gains[i - 1] = min(gains[i - 1], -gains[i]);
+    }
+    ScoreUnit(gains[0])
+}
+
+pub fn see_ge(p: &Position, m: &Move, threshold: ScoreUnit) -> bool {
+    if m.castle().is_some() {
+        return ScoreUnit(0) >= threshold;
+    }
+    let to = m.to();
+    let (gain, piece_type, mut occupied) = first_capture(p, m);
+
+    // Below the threshold even if the exchange stops after the move,
+    // or still above it even if the moved piece is lost.
+    let mut swap = gain - threshold.0;
+    if swap < 0 {
+        return false;
+    }
+    swap = see_value(piece_type) - swap;
+    if swap <= 0 {
+        return true;
+    }
+
+    // res is whether the threshold is reached if the exchange stops after the last
+    // capture, and swap is how far the side that made it stays on its side of the
+    // threshold if it loses the capturing piece too.
+    let mut side = p.side_to_move();
+    let mut res = true;
+    loop {
+        side = side.invert();
+        let (attacker_type, from) = match next_attacker(p, to, occupied, side) {
+            Some(val) => val,
+            None => break,
+        };
+        res = !res;
+        swap = see_value(attacker_type) - swap;
+        // Recapturing cannot change the result any more.
+        if swap < res as i32 {
+            break;
+        }
+        occupied.remove_at(from);
+    }
+    res
+}
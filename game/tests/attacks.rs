@@ -0,0 +1,62 @@
+extern crate game;
+
+use game::{Position, BitBoard};
+
+fn squares(bits: BitBoard) -> Vec<String> {
+    let mut ans: Vec<String> = bits.iter().map(|s| s.to_string()).collect();
+    ans.sort();
+    ans
+}
+
+#[test]
+fn attackers_to() {
+    let pos: Position = "7k/8/5n2/3p4/8/8/2B1R3/4Q1K1 w - - 0 1".parse().unwrap();
+    let e4 = "e4".parse().ok().unwrap();
+    assert_eq!(squares(pos.attackers_to(e4, pos.occupied())), ["c2", "d5", "e2", "f6"]);
+
+    // The queen attacks e4 once the rook in front of it is gone.
+    let mut occupied = pos.occupied();
+    occupied.remove_at("e2".parse().ok().unwrap());
+    assert_eq!(squares(pos.attackers_to(e4, occupied)), ["c2", "d5", "e1", "f6"]);
+}
+
+#[test]
+fn check() {
+    let in_check = |fen: &str| fen.parse::<Position>().unwrap().is_in_check();
+    assert!(!in_check("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"));
+    assert!(in_check("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3"));
+    assert!(in_check("4k3/8/8/8/8/8/3p4/4K3 w - - 0 1"));
+    assert!(in_check("4k3/8/8/8/8/3n4/8/4K3 w - - 0 1"));
+    // The pawn in front of the king does not give check.
+    assert!(!in_check("4k3/8/8/8/8/8/4p3/4K3 w - - 0 1"));
+    // Blocked by the knight.
+    assert!(!in_check("4k3/8/8/8/b7/8/2N5/4K3 w - - 0 1"));
+}
+
+#[test]
+fn castling_through_check() {
+    let castles = |fen: &str| -> Vec<String> {
+        let pos: Position = fen.parse().unwrap();
+        let mut ans: Vec<String> = pos.legal_iter().map(|m| m.to_string())
+                                      .filter(|m| m == "e1g1" || m == "e1c1").collect();
+        ans.sort();
+        ans
+    };
+    assert_eq!(castles("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1"), ["e1c1", "e1g1"]);
+    // f1 is attacked.
+    assert_eq!(castles("r3k2r/8/b7/8/8/8/8/R3K2R w KQkq - 0 1"), ["e1c1"]);
+    // Only the rook passes through b1.
+    assert_eq!(castles("r3k2r/8/8/8/8/n7/8/R3K2R w KQkq - 0 1"), ["e1c1", "e1g1"]);
+    // Cannot castle out of check.
+    assert_eq!(castles("r3k2r/8/8/8/8/3n4/8/R3K2R w KQkq - 0 1"), Vec::<String>::new());
+}
+
+#[test]
+fn checkers() {
+    let checkers_of = |fen: &str| squares(fen.parse::<Position>().unwrap().checkers());
+    assert_eq!(checkers_of("4k3/8/8/8/8/8/8/4K3 w - - 0 1"), Vec::<String>::new());
+    assert_eq!(checkers_of("4k3/8/8/8/7b/8/3n4/4K3 w - - 0 1"), ["h4"]);
+    assert_eq!(checkers_of("4r1k1/8/8/8/8/8/8/2n1K3 w - - 0 1"), ["e8"]);
+    // Double check
+    assert_eq!(checkers_of("4r1k1/8/8/8/8/3n4/8/4K3 w - - 0 1"), ["d3", "e8"]);
+}
@@ -0,0 +1,75 @@
+extern crate game;
+
+use game::{Position, FromTo};
+
+fn castles(pos: &Position) -> Vec<String> {
+    let mut ans: Vec<String> = pos.legal_iter().filter(|m| m.castle().is_some())
+                                  .map(|m| m.to_string()).collect();
+    ans.sort();
+    ans
+}
+
+#[test]
+fn notation() {
+    let mut pos: Position = "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1".parse().unwrap();
+    assert!(!pos.is_chess960());
+    assert_eq!(castles(&pos), ["e1c1", "e1g1"]);
+    for s in &["e1g1", "e1h1"] {
+        let m = s.parse::<FromTo>().ok().unwrap().to_move_with_pos(&pos);
+        assert_eq!(m.to_string(), "e1g1");
+        assert!(pos.legal_iter().any(|x| x == m));
+    }
+
+    // In Chess960 the king takes its own rook.
+    pos.set_chess960(true);
+    assert_eq!(castles(&pos), ["e1a1", "e1h1"]);
+    let m = "e1h1".parse::<FromTo>().ok().unwrap().to_move_with_pos(&pos);
+    assert!(pos.legal_iter().any(|x| x == m));
+    // Only a king move.
+    let m = "e1g1".parse::<FromTo>().ok().unwrap().to_move_with_pos(&pos);
+    assert!(m.castle().is_none());
+}
+
+#[test]
+fn fen() {
+    // Shredder-FEN is written as X-FEN.
+    let pos: Position = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w HAha - 0 1".parse().unwrap();
+    assert!(!pos.is_chess960());
+    assert_eq!(pos.to_fen(), "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+
+    let fens = ["bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w KQkq - 0 1",
+                // The file is needed if the rook is not the outermost one.
+                "1r1k1r1r/pppppppp/8/8/8/8/PPPPPPPP/1R1K1R1R w Ff - 0 1"];
+    for fen in &fens {
+        let pos: Position = fen.parse().unwrap();
+        assert!(pos.is_chess960());
+        assert_eq!(pos.to_fen(), *fen);
+    }
+}
+
+#[test]
+fn make_move() {
+    let cases = [
+        // The king does not move.
+        ("4k3/8/8/8/8/8/8/6KR w H - 0 1", "g1h1", "4k3/8/8/8/8/8/8/5RK1 b - - 1 1"),
+        // The king lands where the rook was.
+        ("4k3/8/8/8/8/8/8/4K1R1 w G - 0 1", "e1g1", "4k3/8/8/8/8/8/8/5RK1 b - - 1 1"),
+        // The rook lands where the king was.
+        ("4k3/8/8/8/8/8/8/RK6 w A - 0 1", "b1a1", "4k3/8/8/8/8/8/8/2KR4 b - - 1 1"),
+    ];
+    for &(fen, s, after) in &cases {
+        let mut pos: Position = fen.parse().unwrap();
+        let m = s.parse::<FromTo>().ok().unwrap().to_move_with_pos(&pos);
+        assert!(m.castle().is_some());
+        assert!(pos.legal_iter().any(|x| x == m), "{} in {}", s, fen);
+        pos.with_move(&m, |p| assert_eq!(p.to_fen(), after));
+        assert_eq!(pos, fen.parse().unwrap());
+    }
+}
+
+#[test]
+fn castling_into_check() {
+    assert_eq!(castles(&"4k3/8/8/8/8/8/8/1R1K4 w B - 0 1".parse().unwrap()), ["d1b1"]);
+    // The rook hides the king from the queen until it moves.
+    assert_eq!(castles(&"4k3/8/8/8/8/8/8/qR1K4 w B - 0 1".parse().unwrap()), Vec::<String>::new());
+}
@@ -0,0 +1,60 @@
+extern crate game;
+
+use game::{Position, ZobristHash};
+
+// Plays the SAN moves, keeping the hashes of the earlier positions like the search does.
+fn play(pos: &mut Position, history: &mut Vec<ZobristHash>, moves: &[&str]) {
+    for san in moves {
+        let m = pos.parse_san(san).unwrap();
+        history.push(pos.hash());
+        pos.make_move(&m);
+    }
+}
+
+#[test]
+fn threefold_repetition() {
+    let mut pos = Position::start();
+    let mut history = Vec::new();
+    play(&mut pos, &mut history, &["Nf3", "Nf6", "Ng1", "Ng8"]);
+    assert_eq!(pos.repetition_count(&history), 1);
+    assert!(!pos.is_draw_by_rule(&history));
+    play(&mut pos, &mut history, &["Nf3", "Nf6", "Ng1"]);
+    // This position occurred after the third ply.
+    assert_eq!(pos.repetition_count(&history), 1);
+    play(&mut pos, &mut history, &["Ng8"]);
+    assert_eq!(pos.repetition_count(&history), 2);
+    assert!(pos.is_draw_by_rule(&history));
+}
+
+#[test]
+fn irreversible_moves() {
+    let mut pos = Position::start();
+    let mut history = Vec::new();
+    play(&mut pos, &mut history, &["Nf3", "Nf6", "Ng1", "Ng8"]);
+    assert_eq!(pos.repetition_count(&history), 1);
+
+    // The same pieces on the same squares, but the halfmove clock was reset
+    // by a capture or a pawn move since, so the earlier positions do not count.
+    let reset: Position = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 3"
+                          .parse().unwrap();
+    assert_eq!(reset.hash(), pos.hash());
+    assert_eq!(reset.repetition_count(&history), 0);
+}
+
+#[test]
+fn fifty_move_rule() {
+    let mut pos: Position = "4k3/8/8/8/8/8/8/R3K3 w - - 99 80".parse().unwrap();
+    assert!(!pos.is_draw_by_fifty_move_rule());
+    assert!(!pos.is_draw_by_rule(&[]));
+    let m = pos.parse_san("Kd2").unwrap();
+    pos.make_move(&m);
+    assert!(pos.is_draw_by_fifty_move_rule());
+    assert!(pos.is_draw_by_rule(&[]));
+
+    // Checkmate on the hundredth ply is not a draw.
+    let mut pos: Position = "k7/8/1K6/8/8/8/8/7R w - - 99 80".parse().unwrap();
+    let m = pos.parse_san("Rh8").unwrap();
+    pos.make_move(&m);
+    assert!(pos.is_checkmated());
+    assert!(!pos.is_draw_by_fifty_move_rule());
+}
@@ -0,0 +1,128 @@
+extern crate game;
+
+use game::{Position, Score, ScoreUnit, EvalParams};
+
+// Swaps the colors and flips the board vertically.
+fn mirror(fen: &str) -> String {
+    let fields: Vec<&str> = fen.split(' ').collect();
+    let swap_case = |ch: char| {
+        if ch.is_uppercase() { ch.to_ascii_lowercase() } else { ch.to_ascii_uppercase() }
+    };
+    let board: Vec<String> = fields[0].split('/').rev()
+                                      .map(|row| row.chars().map(&swap_case).collect())
+                                      .collect();
+    let side = if fields[1] == "w" { "b" } else { "w" };
+    let castling: String = if fields[2] == "-" {
+        "-".to_string()
+    } else {
+        let mut ans: Vec<char> = fields[2].chars().map(&swap_case).collect();
+        ans.sort();
+        ans.into_iter().collect()
+    };
+    let en_passant = if fields[3] == "-" {
+        "-".to_string()
+    } else {
+        fields[3].replace("3", "x").replace("6", "3").replace("x", "6")
+    };
+    format!("{} {} {} {} {} {}", board.join("/"), side, castling, en_passant,
+            fields[4], fields[5])
+}
+
+fn eval_of(fen: &str) -> Score {
+    let mut pos: Position = fen.parse().unwrap();
+    pos.eval()
+}
+
+#[test]
+fn symmetric() {
+    assert_eq!(eval_of("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"),
+               Score::Value(ScoreUnit(0)));
+    let fens = ["r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+                "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+                "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+                "4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1",
+                "4k3/p1p3pp/1p1p2P1/2P5/PP3P2/3P4/5P2/4K3 b - - 0 1"];
+    for fen in &fens {
+        assert_eq!(eval_of(fen), eval_of(&mirror(fen)), "{}", fen);
+    }
+}
+
+#[test]
+fn phase() {
+    // With only pawns left, the king belongs in the center.
+    assert!(eval_of("8/p7/8/8/3K4/8/P7/7k w - - 0 1") >
+            eval_of("8/p7/8/8/8/8/P7/K2k4 w - - 0 1"));
+    // With all the pieces on the board, it should stay behind its pawns.
+    assert!(eval_of("rnbq1rk1/pppppppp/8/8/8/8/PPPPPPPP/RNBQ1RK1 w - - 0 1") >
+            eval_of("rnbq1rk1/pppppppp/8/8/8/4K3/PPPPPPPP/RNBQ1R2 w - - 0 1"));
+}
+
+#[test]
+fn params() {
+    let default = EvalParams::default();
+    let values = default.to_vec();
+    assert_eq!(EvalParams::from_slice(&values), Some(default.clone()));
+    assert_eq!(EvalParams::from_slice(&values[1..]), None);
+
+    // Zero weights make every position equal.
+    let zero = EvalParams::from_slice(&vec![0; values.len()]).unwrap();
+    let mut pos: Position = "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8"
+                            .parse().unwrap();
+    assert_eq!(pos.eval_with(&zero), Score::Value(ScoreUnit(0)));
+    assert_eq!(pos.eval_with(&default), pos.eval());
+}
+
+#[test]
+fn pawn_structure() {
+    // A passed pawn is worth more than one that enemy pawns can stop. Black's pawn
+    // on d6 keeps e4 from being passed, while the one on a6 is passed, but less advanced.
+    assert!(eval_of("4k3/8/p7/8/4P3/8/8/4K3 w - - 0 1") >
+            eval_of("4k3/8/3p4/8/4P3/8/8/4K3 w - - 0 1"));
+    // Doubled and isolated pawns are worse than connected ones.
+    assert!(eval_of("4k3/pp6/8/8/8/8/1PP5/4K3 w - - 0 1") >
+            eval_of("4k3/pp6/8/8/8/2P5/2P5/4K3 w - - 0 1"));
+    // Passed pawns are worth more the further they are.
+    assert!(eval_of("4k3/8/8/8/P7/8/8/4K3 w - - 0 1") >
+            eval_of("4k3/8/8/8/8/8/P7/4K3 w - - 0 1"));
+}
+
+#[test]
+fn pawn_hash() {
+    let start: Position = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"
+                          .parse().unwrap();
+    // Piece moves leave the pawn hash unchanged, and pawn moves change it.
+    let mut pos = start.clone();
+    for m in start.legal_iter() {
+        pos.with_move(&m, |new_pos| {
+            // Only the pawns can move from the second rank.
+            let is_pawn_move = m.to_string().as_bytes()[1] == b'2';
+            assert_eq!(new_pos.pawn_hash() != start.pawn_hash(), is_pawn_move, "{}", m);
+            let parsed: Position = new_pos.to_fen().parse().unwrap();
+            assert_eq!(new_pos.pawn_hash(), parsed.pawn_hash());
+        });
+    }
+    assert_eq!(pos.pawn_hash(), start.pawn_hash());
+}
+
+// Compares the incrementally updated eval with one computed from scratch.
+fn check_incremental(pos: &mut Position, params: &EvalParams, depth: u32) {
+    assert_eq!(pos.eval(), pos.eval_with(params), "{}", pos);
+    if depth == 0 { return; }
+    let moves: Vec<_> = pos.legal_iter().collect();
+    for m in moves {
+        pos.with_move(&m, |new_pos| check_incremental(new_pos, params, depth - 1));
+    }
+}
+
+#[test]
+fn incremental() {
+    let params = EvalParams::default();
+    // Castling, en passant, promotions and captures.
+    let fens = ["r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+                "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+                "n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1"];
+    for fen in &fens {
+        let mut pos: Position = fen.parse().unwrap();
+        check_incremental(&mut pos, &params, 2);
+    }
+}
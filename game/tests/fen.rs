@@ -0,0 +1,52 @@
+extern crate game;
+
+use game::{Position, ParsePosError};
+
+#[test]
+fn round_trip() {
+    let fens = ["rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+                "rnbqkbnr/ppp1pppp/8/8/3pP3/8/PPPP1PPP/RNBQKBNR b Kq e3 0 3",
+                "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 17 52"];
+    for fen in &fens {
+        let pos: Position = fen.parse().unwrap();
+        assert_eq!(pos.to_fen(), *fen);
+        assert_eq!(format!("{}", pos), *fen);
+    }
+}
+
+#[test]
+fn move_counters() {
+    let mut pos: Position = "4k3/8/8/8/8/8/8/4K3 w - - 5 40".parse().unwrap();
+    let m = pos.legal_iter().next().unwrap();
+    pos.make_move(&m);
+    assert!(pos.to_fen().ends_with(" b - - 6 40"), "{}", pos);
+    let m = pos.legal_iter().next().unwrap();
+    pos.make_move(&m);
+    assert!(pos.to_fen().ends_with(" w - - 7 41"), "{}", pos);
+}
+
+#[test]
+fn invalid() {
+    let cases = [
+        ("", ParsePosError::WrongNumberOfFields),
+        ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0", ParsePosError::WrongNumberOfFields),
+        ("rnbqkbnr/pppppppp/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", ParsePosError::WrongNumberOfRanks),
+        ("rnbqkbnr/ppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", ParsePosError::WrongRankLength),
+        ("rnbqkbnr/pppppppp/9/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", ParsePosError::InvalidPiece),
+        ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNX w KQkq - 0 1", ParsePosError::InvalidPiece),
+        ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQ1BNR w kq - 0 1", ParsePosError::WrongNumberOfKings),
+        ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKPNR w KQkq - 0 1", ParsePosError::PawnOnBackRank),
+        ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR x KQkq - 0 1", ParsePosError::InvalidSideToMove),
+        ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkx - 0 1", ParsePosError::InvalidCastling),
+        ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBN1 w KQkq - 0 1", ParsePosError::ImpossibleCastling),
+        ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq e9 0 1", ParsePosError::InvalidEnPassant),
+        ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR b KQkq e3 0 1", ParsePosError::ImpossibleEnPassant),
+        ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - x 1", ParsePosError::InvalidPlyCount),
+        ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 0", ParsePosError::InvalidMoveCount),
+        ("4k3/8/8/8/8/8/8/4RK2 w - - 0 1", ParsePosError::OpponentInCheck),
+    ];
+    for &(fen, err) in &cases {
+        assert_eq!(fen.parse::<Position>(), Err(err), "{}", fen);
+    }
+}
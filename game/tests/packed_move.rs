@@ -0,0 +1,44 @@
+extern crate game;
+
+use std::mem::size_of;
+
+use game::{Position, PackedMove};
+
+#[test]
+fn round_trip() {
+    let fens = ["rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+                "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+                "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3"];
+    for fen in &fens {
+        let pos: Position = fen.parse().unwrap();
+        for m in pos.legal_iter() {
+            let packed = m.pack();
+            assert_eq!((packed.from(), packed.to()), (m.from(), m.to()));
+            assert_eq!(PackedMove::from_u16(packed.to_u16()), Some(packed));
+            assert_eq!(pos.unpack_move(packed), Some(m), "{} in {}", m, fen);
+        }
+    }
+}
+
+#[test]
+fn unplayable() {
+    let start = Position::start();
+    let other: Position = "4k3/8/8/8/8/8/8/R3K2R w KQ - 0 1".parse().unwrap();
+    for m in other.legal_iter() {
+        let is_start_move = start.legal_iter().any(|x| x == m);
+        assert_eq!(start.unpack_move(m.pack()).is_some(), is_start_move, "{}", m);
+    }
+}
+
+#[test]
+fn packed() {
+    assert_eq!(size_of::<Option<PackedMove>>(), 2);
+    assert_eq!(PackedMove::from_u16(0), None);
+
+    // Bits 12 and up only hold the promotion, from 1 for a queen to 4 for a knight.
+    let pos: Position = "4k3/P7/8/8/8/8/8/4K3 w - - 0 1".parse().unwrap();
+    let promotion = pos.parse_san("a8=Q").unwrap().pack().to_u16();
+    let invalid = PackedMove::from_u16((promotion & 0xfff) | (5 << 12)).unwrap();
+    assert_eq!(pos.unpack_move(invalid), None);
+}
@@ -0,0 +1,187 @@
+//! Verifies move generation by counting the leaf nodes of the legal move tree
+//! and comparing them against well-known results.
+
+extern crate game;
+
+use game::{Position, NumPlies, MoveList};
+
+/// `expected[i]` is the number of leaf nodes at depth `i + 1`.
+fn check_perft(fen: &str, expected: &[u64]) {
+    let mut pos: Position = fen.parse().unwrap();
+    for (i, &val) in expected.iter().enumerate() {
+        let depth = NumPlies(i as u32 + 1);
+        assert_eq!(pos.perft(depth), val, "perft({}) of {}", depth.0, fen);
+    }
+    // perft() must leave the position untouched.
+    assert_eq!(pos, fen.parse().unwrap());
+}
+
+#[test]
+fn start_pos() {
+    check_perft("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                &[20, 400, 8902, 197281]);
+}
+
+#[test]
+fn kiwipete() {
+    check_perft("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+                &[48, 2039, 97862]);
+}
+
+#[test]
+fn rook_endgame() {
+    check_perft("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+                &[14, 191, 2812, 43238]);
+}
+
+#[test]
+fn promotions_and_castling() {
+    check_perft("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+                &[6, 264, 9467]);
+    // The same position with colors reversed.
+    check_perft("r2q1rk1/pP1p2pp/Q4n2/bbp1p3/Np6/1B3NBn/pPPP1PPP/R3K2R b KQ - 0 1",
+                &[6, 264, 9467]);
+}
+
+#[test]
+fn underpromotion_with_discovery() {
+    check_perft("rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+                &[44, 1486, 62379]);
+}
+
+#[test]
+fn middlegame() {
+    check_perft("r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+                &[46, 2079, 89890]);
+}
+
+#[test]
+fn en_passant() {
+    // En passant capture would expose the king.
+    check_perft("8/5bk1/8/2Pp4/8/1K6/8/8 w - d6 0 1", &[8, 104, 736, 9287]);
+    check_perft("8/8/1k6/8/2pP4/8/5BK1/8 b - d3 0 1", &[8, 104, 736, 9287]);
+    // En passant capture gives check.
+    check_perft("8/8/1k6/2b5/2pP4/8/5K2/8 b - d3 0 1", &[15, 126, 1928, 13931]);
+}
+
+#[test]
+fn castling() {
+    // Castling gives check.
+    check_perft("5k2/8/8/8/8/8/8/4K2R w K - 0 1", &[15, 66, 1198, 6399]);
+    check_perft("3k4/8/8/8/8/8/8/R3K3 w Q - 0 1", &[16, 71, 1286, 7418]);
+    // Castling rights are lost when rooks are captured.
+    check_perft("r3k2r/1b4bq/8/8/8/8/7B/R3K2R w KQkq - 0 1", &[26, 1141, 27826]);
+    // Castling through attacked squares.
+    check_perft("r3k2r/8/3Q4/8/8/5q2/8/R3K2R b KQkq - 0 1", &[44, 1494, 50509]);
+}
+
+#[test]
+fn promotion() {
+    // Promote out of check.
+    check_perft("2K2r2/4P3/8/8/8/8/8/3k4 w - - 0 1", &[11, 133, 1442, 19174]);
+    // Promote to give check.
+    check_perft("4k3/1P6/8/8/8/8/K7/8 w - - 0 1", &[9, 40, 472, 2661]);
+    // Underpromote to give check.
+    check_perft("8/P1k5/K7/8/8/8/8/8 w - - 0 1", &[6, 27, 273, 1329]);
+}
+
+#[test]
+fn discovered_check() {
+    check_perft("8/8/1P2K3/8/2n5/1q6/8/5k2 b - - 0 1", &[29, 165, 5160, 31961]);
+}
+
+#[test]
+fn stalemate_and_checkmate() {
+    check_perft("K1k5/8/P7/8/8/8/8/8 w - - 0 1", &[2, 6, 13, 63, 382, 2217]);
+    check_perft("8/k1P5/8/1K6/8/8/8/8 w - - 0 1", &[10, 25, 268, 926]);
+    check_perft("8/8/2k5/5q2/5n2/8/5K2/8 b - - 0 1", &[37, 183, 6559, 23527]);
+}
+
+#[test]
+fn divide() {
+    let mut pos = Position::start();
+    let divided = pos.perft_divide(NumPlies(3));
+    assert_eq!(divided.len(), 20);
+    assert_eq!(divided.iter().fold(0, |x, &(_, y)| x + y), 8902);
+    for &(ref m, count) in &divided {
+        let expected = pos.with_move(m, |new_pos| new_pos.perft(NumPlies(2)));
+        assert_eq!(count, expected, "divided count of {}", m);
+    }
+}
+
+// Checks that generate_captures() and generate_quiets() split the legal moves,
+// and that the moves giving check are found.
+fn check_generate(pos: &mut Position, depth: u32) {
+    let mut all = MoveList::new();
+    pos.generate_legal(&mut all);
+    let mut captures = MoveList::new();
+    pos.generate_captures(&mut captures);
+    let mut quiets = MoveList::new();
+    pos.generate_quiets(&mut quiets);
+
+    assert_eq!(captures.len() + quiets.len(), all.len(), "{}", pos);
+    assert!(captures.iter().all(|m| m.is_noisy() && all.contains(m)), "{}", pos);
+    assert!(quiets.iter().all(|m| m.is_quiet() && all.contains(m)), "{}", pos);
+    for m in all.iter() {
+        assert!(pos.is_legal(m), "{} in {}", m, pos);
+        let gives_check = pos.with_move(m, |new_pos| new_pos.is_in_check());
+        assert_eq!(pos.gives_check(m), gives_check, "{} in {}", m, pos);
+    }
+
+    if pos.is_in_check() {
+        let mut evasions = MoveList::new();
+        pos.generate_evasions(&mut evasions);
+        assert_eq!(&evasions[..], &all[..], "{}", pos);
+    }
+    let mut quiet_checks = MoveList::new();
+    pos.generate_quiet_checks(&mut quiet_checks);
+    let expected: Vec<_> = quiets.iter().filter(|m| pos.gives_check(m)).cloned().collect();
+    assert_eq!(&quiet_checks[..], &expected[..], "{}", pos);
+
+    if depth == 0 { return; }
+    for m in all.iter() {
+        pos.with_move(m, |new_pos| check_generate(new_pos, depth - 1));
+    }
+}
+
+#[test]
+fn generate() {
+    let fens = ["r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+                "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+                "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+                "5k2/8/8/8/8/8/8/4K2R w K - 0 1",
+                "8/8/1k6/2b5/2pP4/8/5K2/8 b - d3 0 1",
+                "8/8/1P2K3/8/2n5/1q6/8/5k2 b - - 0 1"];
+    for fen in &fens {
+        let mut pos: Position = fen.parse().unwrap();
+        check_generate(&mut pos, 2);
+    }
+}
+
+#[test]
+fn chess960_start_positions() {
+    // The standard start position in Shredder-FEN.
+    check_perft("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w HAha - 0 1",
+                &[20, 400, 8902, 197281]);
+    // These two are mirrored, which only makes a difference once castling is possible.
+    check_perft("bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w HFhf - 0 1",
+                &[20, 400, 9006, 201143]);
+    check_perft("rkrnnqbb/pppppppp/8/8/8/8/PPPPPPPP/RKRNNQBB w CAca - 0 1",
+                &[20, 400, 9006, 201143]);
+}
+
+#[test]
+fn chess960() {
+    check_perft("bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9",
+                &[21, 528, 12189, 326672]);
+    check_perft("2nnrbkr/p1qppppp/8/1ppb4/6PP/3PP3/PPP2P2/BQNNRBKR w HEhe - 1 9",
+                &[21, 807, 18002, 667366]);
+    check_perft("b1q1rrkb/pppppppp/3nn3/8/P7/1PPP4/4PPPP/BQNNRKRB w GE - 1 9",
+                &[20, 479, 10471, 273318]);
+    check_perft("qbbnnrkr/2pp2pp/p7/1p2pp2/8/P3PP2/1PPP1KPP/QBBNNR1R w hf - 0 9",
+                &[22, 593, 13440, 382958]);
+    check_perft("1nbbnrkr/p1p1ppp1/3p4/1p3P1p/3Pq2P/8/PPP1P1P1/QNBBNRKR w HFhf - 0 9",
+                &[28, 1120, 31058, 1171749]);
+    check_perft("qnbnr1kr/ppp1b1pp/4p3/3p1p2/8/2NPP3/PPP1BPPP/QNB1R1KR w HEhe - 1 9",
+                &[29, 899, 26578, 824055]);
+}
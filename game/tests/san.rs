@@ -0,0 +1,78 @@
+extern crate game;
+
+use game::{Position, ParseSanError};
+
+fn san_of(fen: &str, san: &str) -> String {
+    let pos: Position = fen.parse().unwrap();
+    pos.parse_san(san).unwrap().to_san(&pos)
+}
+
+#[test]
+fn round_trip() {
+    let fens = ["rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+                "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+                "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8"];
+    for fen in &fens {
+        let pos: Position = fen.parse().unwrap();
+        for m in pos.legal_iter() {
+            let san = m.to_san(&pos);
+            assert_eq!(pos.parse_san(&san), Ok(m), "{} in {}", san, fen);
+        }
+    }
+}
+
+#[test]
+fn formatting() {
+    let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+    assert_eq!(san_of(start, "e4"), "e4");
+    assert_eq!(san_of(start, "Nf3"), "Nf3");
+    // Castling
+    let castle = "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1";
+    assert_eq!(san_of(castle, "O-O"), "O-O");
+    assert_eq!(san_of(castle, "0-0-0"), "O-O-O");
+    // Disambiguation by file, by rank, and by both.
+    let knights = "4k3/8/8/8/8/8/8/N1N1K2N w - - 0 1";
+    assert_eq!(san_of(knights, "Nab3"), "Nab3");
+    let rooks = "R7/7k/8/8/8/8/8/R3K3 w - - 0 1";
+    assert_eq!(san_of(rooks, "R1a4"), "R1a4");
+    let queens = "k7/8/8/8/8/2Q1Q3/8/2Q1K3 w - - 0 1";
+    assert_eq!(san_of(queens, "Qc3d2"), "Qc3d2");
+    // Captures, en passant and promotions
+    let ep = "4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1";
+    assert_eq!(san_of(ep, "exd6"), "exd6");
+    let promote = "1n2k3/P7/8/8/8/8/8/4K3 w - - 0 1";
+    assert_eq!(san_of(promote, "a8=Q"), "a8=Q");
+    assert_eq!(san_of(promote, "axb8=Q"), "axb8=Q+");
+    assert_eq!(san_of(promote, "axb8N"), "axb8=N");
+    // Check and checkmate
+    let mate = "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1";
+    assert_eq!(san_of(mate, "Ra8"), "Ra8#");
+    assert_eq!(san_of(mate, "Ra7"), "Ra7");
+    assert_eq!(san_of(mate, "Rf1"), "Rf1");
+}
+
+#[test]
+fn errors() {
+    let knights = "4k3/8/8/8/8/8/8/N1N1K2N w - - 0 1";
+    let pos: Position = knights.parse().unwrap();
+    assert_eq!(pos.parse_san("Nb3"), Err(ParseSanError::AmbiguousMove));
+    assert_eq!(pos.parse_san("Nd4"), Err(ParseSanError::IllegalMove));
+    assert_eq!(pos.parse_san("O-O"), Err(ParseSanError::IllegalMove));
+    assert_eq!(pos.parse_san("Xe4"), Err(ParseSanError::InvalidSyntax));
+    assert_eq!(pos.parse_san("Nb9"), Err(ParseSanError::InvalidSyntax));
+    assert_eq!(pos.parse_san(""), Err(ParseSanError::InvalidSyntax));
+
+    // The capture mark must be there exactly for captures.
+    let pos: Position = "4k3/8/8/3p4/4P3/5n2/8/2K3N1 w - - 0 1".parse().unwrap();
+    assert!(pos.parse_san("exd5").is_ok());
+    assert_eq!(pos.parse_san("ed5"), Err(ParseSanError::IllegalMove));
+    assert_eq!(pos.parse_san("d5"), Err(ParseSanError::IllegalMove));
+    assert!(pos.parse_san("Nxf3").is_ok());
+    assert_eq!(pos.parse_san("Nf3"), Err(ParseSanError::IllegalMove));
+    assert!(pos.parse_san("Ne2").is_ok());
+    assert_eq!(pos.parse_san("Nxe2"), Err(ParseSanError::IllegalMove));
+    assert_eq!(pos.parse_san("xe5"), Err(ParseSanError::IllegalMove));
+    let ep: Position = "4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1".parse().unwrap();
+    assert_eq!(ep.parse_san("ed6"), Err(ParseSanError::IllegalMove));
+}
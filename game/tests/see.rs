@@ -0,0 +1,70 @@
+extern crate game;
+
+use game::{Position, ScoreUnit};
+
+fn see_of(fen: &str, san: &str) -> i32 {
+    let pos: Position = fen.parse().unwrap();
+    let m = pos.parse_san(san).unwrap();
+    pos.see(&m).0
+}
+
+#[test]
+fn captures() {
+    // Undefended pawn
+    assert_eq!(see_of("1k1r4/1pp4p/p7/4p3/8/P5P1/1PP4P/2K1R3 w - - 0 1", "Rxe5"), 100);
+    // Knight for a pawn
+    assert_eq!(see_of("1k1r3q/1ppn3p/p4b2/4p3/8/P2N2P1/1PP1R1BP/2K1Q3 w - - 0 1", "Nxe5"), -200);
+    // En passant and promotion
+    assert_eq!(see_of("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "exd6"), 100);
+    assert_eq!(see_of("4k3/P7/8/8/8/8/8/4K3 w - - 0 1", "a8=Q"), 800);
+    // A quiet move to an attacked square
+    assert_eq!(see_of("4k3/8/4p3/8/8/8/8/3QK3 w - - 0 1", "Qd5"), -900);
+    assert_eq!(see_of("4k3/8/8/8/8/8/8/3QK3 w - - 0 1", "Qd5"), 0);
+}
+
+#[test]
+fn x_rays() {
+    // The rook behind the first one recaptures.
+    assert_eq!(see_of("3r2k1/8/8/3p4/8/8/3R4/3R2K1 w - - 0 1", "Rxd5"), 100);
+    assert_eq!(see_of("3r2k1/3r4/8/3p4/8/8/3R4/3R2K1 w - - 0 1", "Rxd5"), -400);
+    // So does the queen behind the bishop.
+    assert_eq!(see_of("4r1k1/8/4n3/8/8/1B6/Q7/6K1 w - - 0 1", "Bxe6"), 300);
+    assert_eq!(see_of("4r1k1/8/4n3/8/8/1B6/8/6K1 w - - 0 1", "Bxe6"), 0);
+}
+
+#[test]
+fn king_recaptures() {
+    // The king may only recapture an undefended piece.
+    assert_eq!(see_of("8/8/4k3/3p4/8/2N5/8/6K1 w - - 0 1", "Nxd5"), -200);
+    assert_eq!(see_of("8/8/4k3/3p4/8/2N5/8/3R2K1 w - - 0 1", "Nxd5"), 100);
+}
+
+#[test]
+fn threshold() {
+    let pos: Position = "1k1r3q/1ppn3p/p4b2/4p3/8/P2N2P1/1PP1R1BP/2K1Q3 w - - 0 1"
+                        .parse().unwrap();
+    let m = pos.parse_san("Nxe5").unwrap();
+    assert!(pos.see_ge(&m, ScoreUnit(-200)));
+    assert!(!pos.see_ge(&m, ScoreUnit(0)));
+}
+
+#[test]
+fn threshold_matches_see() {
+    let fens = ["1k1r3q/1ppn3p/p4b2/4p3/8/P2N2P1/1PP1R1BP/2K1Q3 w - - 0 1",
+                "3r2k1/3r4/8/3p4/8/8/3R4/3R2K1 w - - 0 1",
+                "4r1k1/8/4n3/8/8/1B6/Q7/6K1 w - - 0 1",
+                "8/8/4k3/3p4/8/2N5/8/3R2K1 w - - 0 1",
+                "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+                "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1"];
+    for fen in &fens {
+        let pos: Position = fen.parse().unwrap();
+        for m in pos.legal_iter() {
+            let see = pos.see(&m);
+            for &diff in &[-1000, -101, -100, -99, -1, 0, 1, 99, 100, 101, 1000] {
+                let threshold = ScoreUnit(see.0 + diff);
+                assert_eq!(pos.see_ge(&m, threshold), see >= threshold,
+                           "{} with threshold {} in {}", m, threshold.0, fen);
+            }
+        }
+    }
+}
@@ -0,0 +1,11 @@
+//! Reading and writing games in Portable Game Notation (PGN).
+
+extern crate game;
+
+pub use types::{Game, GameResult, Variation, AnnotatedMove};
+pub use read::{read_games, ParsePgnError};
+pub use write::write_game;
+
+mod types;
+mod read;
+mod write;
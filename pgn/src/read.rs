@@ -0,0 +1,327 @@
+use std::iter::Peekable;
+use std::str::Chars;
+use std::fmt;
+
+use game::{Position, NumPlies, ParsePosError, ParseSanError};
+
+use types::{Game, GameResult, Variation, AnnotatedMove};
+
+/// The reason why PGN text was rejected.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum ParsePgnError {
+    InvalidTag,
+    UnterminatedComment,
+    UnbalancedVariation,
+    // A variation or a NAG before the first move of a line.
+    MisplacedVariation,
+    MisplacedNag,
+    InvalidNag,
+    InvalidFen(ParsePosError),
+    // The ply is 1-based and counted from the start position of the game.
+    InvalidMove(NumPlies, String, ParseSanError),
+}
+impl fmt::Display for ParsePgnError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ParsePgnError::InvalidTag => write!(f, "invalid tag pair"),
+            ParsePgnError::UnterminatedComment => write!(f, "unterminated comment"),
+            ParsePgnError::UnbalancedVariation => write!(f, "unbalanced parentheses"),
+            ParsePgnError::MisplacedVariation => write!(f, "variation without a move to replace"),
+            ParsePgnError::MisplacedNag => write!(f, "NAG without a move before it"),
+            ParsePgnError::InvalidNag => write!(f, "invalid NAG"),
+            ParsePgnError::InvalidFen(e) => write!(f, "invalid FEN tag: {}", e),
+            ParsePgnError::InvalidMove(ply, ref san, e) =>
+                write!(f, "invalid move {} at ply {}: {}", san, ply.0, e),
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+enum Token {
+    Tag(String, String),
+    StartVariation,
+    EndVariation,
+    Result(GameResult),
+    Comment(String),
+    Nag(u8),
+    San(String),
+}
+
+// Comments are kept with single spaces between words,
+// since line breaks in PGN carry no meaning.
+fn normalize_comment(comment: &str) -> String {
+    comment.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+// The NAGs that the suffix annotations of moves stand for.
+fn suffix_nag(suffix: &str) -> Option<u8> {
+    Some(match suffix {
+        "!" => 1,
+        "?" => 2,
+        "!!" => 3,
+        "??" => 4,
+        "!?" => 5,
+        "?!" => 6,
+        _ => return None,
+    })
+}
+
+struct Lexer<'a>(Peekable<Chars<'a>>);
+impl<'a> Lexer<'a> {
+    fn next_token(&mut self) -> Result<Option<Token>, ParsePgnError> {
+        loop {
+            let ch = match self.0.next() {
+                Some(val) => val,
+                None => return Ok(None),
+            };
+            match ch {
+                '[' => return self.tag().map(Some),
+                '{' => {
+                    let mut comment = String::new();
+                    loop {
+                        match self.0.next() {
+                            Some('}') => break,
+                            Some(x) => comment.push(x),
+                            None => return Err(ParsePgnError::UnterminatedComment),
+                        }
+                    }
+                    return Ok(Some(Token::Comment(normalize_comment(&comment))));
+                },
+                // Rest of line comments
+                ';' => {
+                    let comment: String = self.0.by_ref().take_while(|&x| x != '\n').collect();
+                    return Ok(Some(Token::Comment(normalize_comment(&comment))));
+                },
+                // Lines escaped with '%'
+                '%' => {
+                    let _ = self.0.by_ref().any(|x| x == '\n');
+                },
+                '(' => return Ok(Some(Token::StartVariation)),
+                ')' => return Ok(Some(Token::EndVariation)),
+                '*' => return Ok(Some(Token::Result(GameResult::Unknown))),
+                '$' => {
+                    let mut digits = String::new();
+                    while let Some(&x) = self.0.peek() {
+                        if !x.is_ascii_digit() {
+                            break;
+                        }
+                        digits.push(x);
+                        self.0.next();
+                    }
+                    let nag = try!(digits.parse().map_err(|_| ParsePgnError::InvalidNag));
+                    return Ok(Some(Token::Nag(nag)));
+                },
+                _ if ch.is_alphanumeric() => {
+                    let mut symbol = ch.to_string();
+                    while let Some(&x) = self.0.peek() {
+                        if x.is_alphanumeric() || "_+#=:-/!?".contains(x) {
+                            symbol.push(x);
+                            self.0.next();
+                        } else {
+                            break;
+                        }
+                    }
+                    if let Ok(result) = symbol.parse() {
+                        return Ok(Some(Token::Result(result)));
+                    }
+                    // Skip move numbers
+                    if !symbol.chars().all(|x| x.is_ascii_digit()) {
+                        return Ok(Some(Token::San(symbol)));
+                    }
+                },
+                _ => {},
+            }
+        }
+    }
+
+    fn tag(&mut self) -> Result<Token, ParsePgnError> {
+        self.skip_whitespace();
+        let mut name = String::new();
+        while let Some(&x) = self.0.peek() {
+            if x.is_alphanumeric() || x == '_' {
+                name.push(x);
+                self.0.next();
+            } else {
+                break;
+            }
+        }
+        self.skip_whitespace();
+        if name.is_empty() || self.0.next() != Some('"') {
+            return Err(ParsePgnError::InvalidTag);
+        }
+        let mut value = String::new();
+        loop {
+            match self.0.next() {
+                Some('"') => break,
+                Some('\\') => match self.0.next() {
+                    Some(x) => value.push(x),
+                    None => return Err(ParsePgnError::InvalidTag),
+                },
+                Some(x) => value.push(x),
+                None => return Err(ParsePgnError::InvalidTag),
+            }
+        }
+        self.skip_whitespace();
+        if self.0.next() != Some(']') {
+            return Err(ParsePgnError::InvalidTag);
+        }
+        Ok(Token::Tag(name, value))
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.0.peek().is_some_and(|x| x.is_whitespace()) {
+            self.0.next();
+        }
+    }
+}
+
+// A line that is being read, with the position after its moves.
+struct Line {
+    variation: Variation,
+    pos: Position,
+    // The position before the last move, which its variations start from.
+    prev_pos: Option<Position>,
+    // The number of plies from the start position of the game to pos.
+    ply: u32,
+}
+impl Line {
+    fn new(pos: Position, ply: u32) -> Line {
+        Line { variation: Variation::new(), pos: pos, prev_pos: None, ply: ply }
+    }
+}
+
+// A game whose tags were read, and which may be in the middle of its movetext.
+struct PartialGame {
+    game: Game,
+    // The main line and the variations that are open, innermost last.
+    // Empty until the movetext begins.
+    lines: Vec<Line>,
+}
+impl PartialGame {
+    fn new() -> PartialGame {
+        PartialGame { game: Game::new(), lines: Vec::new() }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.game.tags.is_empty() && self.lines.is_empty()
+    }
+
+    // The innermost line. Sets up the start position from the tags when the movetext begins.
+    fn line_mut(&mut self) -> Result<&mut Line, ParsePgnError> {
+        if self.lines.is_empty() {
+            if let Some(fen) = self.game.tag("FEN") {
+                self.game.start_pos = try!(fen.parse().map_err(ParsePgnError::InvalidFen));
+            }
+            self.lines.push(Line::new(self.game.start_pos.clone(), 0));
+        }
+        Ok(self.lines.last_mut().unwrap())
+    }
+
+    // Starts a variation of the last move of the innermost line.
+    fn start_variation(&mut self) -> Result<(), ParsePgnError> {
+        let new_line = {
+            let line = try!(self.line_mut());
+            match line.prev_pos {
+                Some(ref pos) => Line::new(pos.clone(), line.ply - 1),
+                None => return Err(ParsePgnError::MisplacedVariation),
+            }
+        };
+        self.lines.push(new_line);
+        Ok(())
+    }
+
+    fn end_variation(&mut self) -> Result<(), ParsePgnError> {
+        if self.lines.len() < 2 {
+            return Err(ParsePgnError::UnbalancedVariation);
+        }
+        let line = self.lines.pop().unwrap();
+        let parent = self.lines.last_mut().unwrap();
+        parent.variation.moves.last_mut().unwrap().variations.push(line.variation);
+        Ok(())
+    }
+
+    fn add_move(&mut self, token: &str) -> Result<(), ParsePgnError> {
+        // Suffix annotations such as "!?" are kept as NAGs.
+        let san = token.trim_end_matches(&['!', '?'][..]);
+        let nag = suffix_nag(&token[san.len()..]);
+        let line = try!(self.line_mut());
+        let m = try!(line.pos.parse_san(san).map_err(|e| {
+            ParsePgnError::InvalidMove(NumPlies(line.ply + 1), token.to_string(), e)
+        }));
+        line.prev_pos = Some(line.pos.clone());
+        line.pos.make_move(&m);
+        line.ply += 1;
+        let mut annotated = AnnotatedMove::new(m);
+        annotated.nags.extend(nag);
+        line.variation.moves.push(annotated);
+        Ok(())
+    }
+
+    fn add_comment(&mut self, comment: String) -> Result<(), ParsePgnError> {
+        let variation = &mut try!(self.line_mut()).variation;
+        match variation.moves.last_mut() {
+            Some(x) => x.comments.push(comment),
+            None => variation.comments.push(comment),
+        }
+        Ok(())
+    }
+
+    fn add_nag(&mut self, nag: u8) -> Result<(), ParsePgnError> {
+        match try!(self.line_mut()).variation.moves.last_mut() {
+            Some(x) => x.nags.push(nag),
+            None => return Err(ParsePgnError::MisplacedNag),
+        }
+        Ok(())
+    }
+
+    fn finish(mut self, result: Option<GameResult>) -> Result<Game, ParsePgnError> {
+        if self.lines.len() > 1 {
+            return Err(ParsePgnError::UnbalancedVariation);
+        }
+        try!(self.line_mut());
+        self.game.main_line = self.lines.pop().unwrap().variation;
+        self.game.result = match result {
+            Some(val) => val,
+            None => self.game.tag("Result").and_then(|x| x.parse().ok())
+                                           .unwrap_or(GameResult::Unknown),
+        };
+        Ok(self.game)
+    }
+}
+
+/// Reads all games in the PGN text, including the comments, NAGs and variations.
+/// The moves of variations must be legal as well.
+pub fn read_games(s: &str) -> Result<Vec<Game>, ParsePgnError> {
+    let mut lexer = Lexer(s.chars().peekable());
+    let mut ans = Vec::new();
+    let mut curr = PartialGame::new();
+
+    while let Some(token) = try!(lexer.next_token()) {
+        match token {
+            Token::Tag(name, value) => {
+                // A tag after the movetext starts a new game.
+                if !curr.lines.is_empty() {
+                    ans.push(try!(curr.finish(None)));
+                    curr = PartialGame::new();
+                }
+                curr.game.set_tag(&name, &value);
+            },
+            Token::StartVariation => try!(curr.start_variation()),
+            Token::EndVariation => try!(curr.end_variation()),
+            Token::Result(result) => {
+                // Results at the end of variations are ignored.
+                if curr.lines.len() <= 1 {
+                    ans.push(try!(curr.finish(Some(result))));
+                    curr = PartialGame::new();
+                }
+            },
+            Token::Comment(comment) => try!(curr.add_comment(comment)),
+            Token::Nag(nag) => try!(curr.add_nag(nag)),
+            Token::San(san) => try!(curr.add_move(&san)),
+        }
+    }
+    if !curr.is_empty() {
+        ans.push(try!(curr.finish(None)));
+    }
+    Ok(ans)
+}
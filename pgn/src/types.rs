@@ -0,0 +1,119 @@
+use std::str::FromStr;
+use std::fmt;
+
+use game::{Position, Move};
+
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum GameResult {
+    WhiteWins,
+    BlackWins,
+    Draw,
+    Unknown,
+}
+impl fmt::Display for GameResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", match *self {
+            GameResult::WhiteWins => "1-0",
+            GameResult::BlackWins => "0-1",
+            GameResult::Draw      => "1/2-1/2",
+            GameResult::Unknown   => "*",
+        })
+    }
+}
+impl FromStr for GameResult {
+    type Err = ();
+    fn from_str(s: &str) -> Result<GameResult, ()> {
+        match s {
+            "1-0"     => Ok(GameResult::WhiteWins),
+            "0-1"     => Ok(GameResult::BlackWins),
+            "1/2-1/2" => Ok(GameResult::Draw),
+            "*"       => Ok(GameResult::Unknown),
+            _ => Err(()),
+        }
+    }
+}
+
+/// A move of the movetext, with what is written after it.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct AnnotatedMove {
+    pub m: Move,
+    /// Numeric Annotation Glyphs, such as 1 for "!".
+    pub nags: Vec<u8>,
+    pub comments: Vec<String>,
+    /// Alternatives to this move, which start from the position before it.
+    pub variations: Vec<Variation>,
+}
+impl AnnotatedMove {
+    pub fn new(m: Move) -> AnnotatedMove {
+        AnnotatedMove {
+            m: m,
+            nags: Vec::new(),
+            comments: Vec::new(),
+            variations: Vec::new(),
+        }
+    }
+}
+
+/// A line of moves, either the main line of a game or a variation.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Variation {
+    /// The comments before the first move.
+    pub comments: Vec<String>,
+    pub moves: Vec<AnnotatedMove>,
+}
+impl Variation {
+    pub fn new() -> Variation {
+        Variation { comments: Vec::new(), moves: Vec::new() }
+    }
+}
+impl Default for Variation {
+    fn default() -> Variation { Variation::new() }
+}
+
+/// A single game.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Game {
+    /// Tag pairs, in the order they appear.
+    pub tags: Vec<(String, String)>,
+    pub start_pos: Position,
+    pub main_line: Variation,
+    pub result: GameResult,
+}
+impl Game {
+    pub fn new() -> Game {
+        Game {
+            tags: Vec::new(),
+            start_pos: Position::start(),
+            main_line: Variation::new(),
+            result: GameResult::Unknown,
+        }
+    }
+
+    /// The moves of the main line.
+    pub fn moves(&self) -> Vec<Move> {
+        self.main_line.moves.iter().map(|x| x.m).collect()
+    }
+
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags.iter().find(|x| x.0 == name).map(|x| &*x.1)
+    }
+    pub fn set_tag(&mut self, name: &str, value: &str) {
+        if let Some(x) = self.tags.iter_mut().find(|x| x.0 == name) {
+            x.1 = value.to_string();
+            return;
+        }
+        self.tags.push((name.to_string(), value.to_string()));
+    }
+
+    /// The position after all the moves of the main line were played.
+    pub fn end_pos(&self) -> Position {
+        let mut pos = self.start_pos.clone();
+        for x in &self.main_line.moves {
+            pos.make_move(&x.m);
+        }
+        pos
+    }
+}
+impl Default for Game {
+    fn default() -> Game { Game::new() }
+}
@@ -0,0 +1,97 @@
+use game::{Position, White, Black};
+
+use types::{Game, Variation};
+
+// PGN export format lines should not be longer than this.
+const MAX_LINE_LEN: usize = 79;
+
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+// Adds the comment as words, so that long comments can be split across lines.
+// Comments in braces cannot contain a closing brace.
+fn push_comment(words: &mut Vec<String>, comment: &str) {
+    let mut comment_words: Vec<String> = comment.replace('}', "").split_whitespace()
+                                                .map(|x| x.to_string()).collect();
+    if comment_words.is_empty() {
+        comment_words.push(String::new());
+    }
+    comment_words[0].insert(0, '{');
+    comment_words.last_mut().unwrap().push('}');
+    words.extend(comment_words);
+}
+
+// The words of the movetext of the variation, which starts from pos.
+fn write_variation(variation: &Variation, mut pos: Position) -> Vec<String> {
+    let mut words = Vec::new();
+    for comment in &variation.comments {
+        push_comment(&mut words, comment);
+    }
+    // Black moves are numbered at the start of a line and after comments and variations.
+    let mut needs_number = true;
+    for x in &variation.moves {
+        match pos.side_to_move() {
+            White => words.push(format!("{}.", pos.move_count().0)),
+            Black if needs_number => words.push(format!("{}...", pos.move_count().0)),
+            Black => {},
+        }
+        words.push(x.m.to_san(&pos));
+        for nag in &x.nags {
+            words.push(format!("${}", nag));
+        }
+        for comment in &x.comments {
+            push_comment(&mut words, comment);
+        }
+        for sub in &x.variations {
+            let mut sub_words = write_variation(sub, pos.clone());
+            if sub_words.is_empty() {
+                words.push("()".to_string());
+            } else {
+                sub_words[0].insert(0, '(');
+                sub_words.last_mut().unwrap().push(')');
+                words.extend(sub_words);
+            }
+        }
+        needs_number = !x.comments.is_empty() || !x.variations.is_empty();
+        pos.make_move(&x.m);
+    }
+    words
+}
+
+/// Writes a game in PGN export format, followed by an empty line.
+pub fn write_game(game: &Game) -> String {
+    let mut ans = String::new();
+
+    let mut tags = game.tags.clone();
+    let is_start = game.start_pos == Position::start();
+    if !is_start && game.tag("FEN").is_none() {
+        tags.push(("SetUp".to_string(), "1".to_string()));
+        tags.push(("FEN".to_string(), game.start_pos.to_fen()));
+    }
+    for (name, value) in &tags {
+        ans.push_str(&format!("[{} \"{}\"]\n", name, escape(value)));
+    }
+    if !tags.is_empty() {
+        ans.push('\n');
+    }
+
+    let mut words = write_variation(&game.main_line, game.start_pos.clone());
+    words.push(game.result.to_string());
+
+    let mut line_len = 0;
+    for word in &words {
+        if line_len != 0 && line_len + 1 + word.len() > MAX_LINE_LEN {
+            ans.push('\n');
+            line_len = 0;
+        }
+        if line_len != 0 {
+            ans.push(' ');
+            line_len += 1;
+        }
+        ans.push_str(word);
+        line_len += word.len();
+    }
+    ans.push_str("\n\n");
+    ans
+}
@@ -0,0 +1,102 @@
+extern crate game;
+extern crate pgn;
+
+use game::{NumPlies, ParseSanError};
+use pgn::{read_games, write_game, GameResult, ParsePgnError, Game};
+
+const SAMPLE: &str = r#"
+[Event "Casual \"blitz\""]
+[Site "?"]
+[White "A"]
+[Black "B"]
+[Result "1-0"]
+
+1. e4 {best by test} e5 2. Nf3 $1 Nc6 (2... d6 3. d4 (3. Bc4) exd4) 3. Bb5 a6
+; a comment until the end of the line
+4. Ba4!? Nf6 5. O-O Be7 1-0
+
+[Event "Second"]
+[SetUp "1"]
+[FEN "4k3/8/8/8/8/8/4P3/4K3 b - - 0 30"]
+
+30... Kd7 31. e4 *
+"#;
+
+#[test]
+fn read() {
+    let games = read_games(SAMPLE).unwrap();
+    assert_eq!(games.len(), 2);
+
+    assert_eq!(games[0].tag("Event"), Some("Casual \"blitz\""));
+    assert_eq!(games[0].moves().len(), 10);
+    assert_eq!(games[0].result, GameResult::WhiteWins);
+    assert_eq!(games[0].end_pos().to_fen(),
+               "r1bqk2r/1pppbppp/p1n2n2/4p3/B3P3/5N2/PPPP1PPP/RNBQ1RK1 w kq - 4 6");
+
+    assert_eq!(games[1].moves().len(), 2);
+    assert_eq!(games[1].result, GameResult::Unknown);
+
+    // Comments, NAGs and variations
+    let main_line = &games[0].main_line;
+    assert_eq!(main_line.moves[0].comments, ["best by test"]);
+    assert_eq!(main_line.moves[2].nags, [1]);
+    assert_eq!(main_line.moves[5].comments, ["a comment until the end of the line"]);
+    // "Ba4!?"
+    assert_eq!(main_line.moves[6].nags, [5]);
+    let variations = &main_line.moves[3].variations;
+    assert_eq!(variations.len(), 1);
+    let sans: Vec<String> = variations[0].moves.iter().map(|x| x.m.to_string()).collect();
+    assert_eq!(sans, ["d7d6", "d2d4", "e5d4"]);
+    assert_eq!(variations[0].moves[1].variations[0].moves[0].m.to_string(), "f1c4");
+    assert_eq!(games[1].end_pos().to_fen(), "8/3k4/8/8/4P3/8/8/4K3 b - e3 0 31");
+}
+
+#[test]
+fn round_trip() {
+    let games = read_games(SAMPLE).unwrap();
+    for game in &games {
+        let written = write_game(game);
+        let read_back = read_games(&written).unwrap();
+        assert_eq!(read_back, vec![game.clone()]);
+    }
+    assert!(write_game(&games[1]).contains("\n30... Kd7 31. e4 *\n"));
+    assert!(write_game(&games[0]).replace('\n', " ").contains(
+        "1. e4 {best by test} 1... e5 2. Nf3 $1 Nc6 (2... d6 3. d4 (3. Bc4) 3... exd4) 3. Bb5"));
+}
+
+#[test]
+fn round_trip_variations() {
+    let text = "1. d4 {A long comment that is written over more than one line, since it does not \
+                fit on a single one} ({Before the move} 1. e4 e5 (1... c5 $14 {Sicilian} \
+                (1... e6) 2. Nf3) 2. Nf3 $1 $32) 1... d5 () 2. c4 *";
+    let games = read_games(text).unwrap();
+    assert_eq!(games.len(), 1);
+    let game: &Game = &games[0];
+    assert_eq!(game.main_line.moves[0].variations[0].comments, ["Before the move"]);
+    let written = write_game(game);
+    assert!(written.lines().all(|x| x.len() <= 79), "{}", written);
+    assert_eq!(read_games(&written).unwrap(), games);
+    // Writing it again gives the same text.
+    assert_eq!(write_game(&read_games(&written).unwrap()[0]), written);
+}
+
+#[test]
+fn errors() {
+    assert_eq!(read_games("1. e4 e5 2. Ke3 *"),
+               Err(ParsePgnError::InvalidMove(NumPlies(3), "Ke3".to_string(),
+                                              ParseSanError::IllegalMove)));
+    assert_eq!(read_games("1. e4 (1. d4 *"), Err(ParsePgnError::UnbalancedVariation));
+    assert_eq!(read_games("1. e4 e5) *"), Err(ParsePgnError::UnbalancedVariation));
+    // Moves inside variations must be legal too.
+    assert_eq!(read_games("1. e4 e5 (1... Ke7 2. Ke2) *"),
+               Err(ParsePgnError::InvalidMove(NumPlies(2), "Ke7".to_string(),
+                                              ParseSanError::IllegalMove)));
+    assert_eq!(read_games("1. e4 e5 2. Nf3 (2. d4 (2. Nc3 Nc6 3. Nb5 a6 4. Nxd6) d5) *"),
+               Err(ParsePgnError::InvalidMove(NumPlies(7), "Nxd6".to_string(),
+                                              ParseSanError::IllegalMove)));
+    assert_eq!(read_games("(1. e4) 1. d4 *"), Err(ParsePgnError::MisplacedVariation));
+    assert_eq!(read_games("$1 1. d4 *"), Err(ParsePgnError::MisplacedNag));
+    assert_eq!(read_games("1. d4 $300 *"), Err(ParsePgnError::InvalidNag));
+    assert_eq!(read_games("1. e4 {oops"), Err(ParsePgnError::UnterminatedComment));
+    assert_eq!(read_games("[Event \"x\" 1. e4"), Err(ParsePgnError::InvalidTag));
+}
@@ -1,19 +1,19 @@
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::Ordering;
 
-use game::{Move, Position, Score, ScoreUnit, NumPlies};
+use game::{Move, Position, Score, ScoreUnit, NumPlies, NumMoves};
 use types::InnerData;
-use negamax::{negamax_root, Bound};
-use transposition_table::TranspositionTable;
+use negamax::{negamax_root, Bound, Context};
 
 pub fn depth_limited_search(pos: &mut Position,
                             search_moves: &[Move],
                             depth: NumPlies,
-                            table: &mut TranspositionTable,
-                            is_killed: &AtomicBool) -> Option<(Score, Move, InnerData)> {
+                            ctx: &mut Context,
+                            curr_move_fn: &mut FnMut(&Move, NumMoves))
+                            -> Option<(Score, Vec<Move>, InnerData)> {
     assert!(!search_moves.is_empty());
     assert!(depth.0 >= 1);
 
-    let guess_score = table.get(pos).map_or(ScoreUnit(0), |x| {
+    let guess_score = ctx.table.get(pos).map_or(ScoreUnit(0), |x| {
         if let Bound::Exact(Score::Value(val)) = x.bound {
             val
         } else {
@@ -25,30 +25,33 @@ pub fn depth_limited_search(pos: &mut Position,
 
     let mut alpha_window = 0;
     let mut beta_window = 0;
-    let mut best_score_move_opt = None;
+    let mut best_score_pv_opt = None;
     let mut data = InnerData::one_node();
 
-    while best_score_move_opt.is_none() {
+    while best_score_pv_opt.is_none() {
         let curr_alpha = aspiration_width.get(alpha_window)
                                          .map(|&diff| Score::Value(guess_score - diff));
         let curr_beta  = aspiration_width.get(beta_window)
                                          .map(|&diff| Score::Value(guess_score + diff));
-        let curr_ans = negamax_root(pos, curr_alpha, curr_beta,
-                                    depth, table, is_killed, search_moves);
-        if is_killed.load(Ordering::Relaxed) {
+        let curr_ans = negamax_root(pos, curr_alpha, curr_beta, depth, ctx,
+                                    search_moves, curr_move_fn);
+        if ctx.is_killed.load(Ordering::Relaxed) {
             // Thread killed. Bail out
             return None;
         }
-        let (curr_bound, curr_best_move_opt, curr_data) = curr_ans;
+        let (curr_bound, curr_pv, curr_data) = curr_ans;
         data = data.combine(curr_data);
         match curr_bound {
             Bound::Lower(_) => beta_window += 1,
             Bound::Upper(_) => alpha_window += 1,
-            Bound::Exact(x) => best_score_move_opt = Some((x, curr_best_move_opt.unwrap())),
+            Bound::Exact(x) => {
+                assert!(!curr_pv.is_empty(), "no best move at the root");
+                best_score_pv_opt = Some((x, curr_pv));
+            },
         }
     }
     
-    let (best_score, best_move) = best_score_move_opt.unwrap();
+    let (best_score, pv) = best_score_pv_opt.unwrap();
 
-    Some((best_score, best_move, data))
+    Some((best_score, pv, data))
 }
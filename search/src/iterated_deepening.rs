@@ -1,39 +1,88 @@
 use std::sync::mpsc::Sender;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicIsize, Ordering};
 use std::sync::Arc;
+use std::cmp::min;
+use std::time::{Duration, Instant};
 
-use game::{Position, Move, NumPlies};
+use game::{Move, Score, NumPlies, NumMoves};
 
-use types::{InnerData, Data, Report};
+use types::{State, InnerData, Data, Info, Report};
 use transposition_table::TranspositionTable;
 use depth_limited_search::depth_limited_search;
+use negamax::Context;
+use move_picker::Heuristics;
 
-pub fn iterated_deepening(pos: Position,
+pub fn iterated_deepening(state: State,
                           search_moves: &[Move],
-                          mut table: TranspositionTable,
-                          tx: Sender<Report>,
-                          is_killed: Arc<AtomicBool>) {
-    let mut best_score;
-    let mut best_move;
+                          table: Arc<TranspositionTable>,
+                          tx: Sender<Info>,
+                          is_killed: Arc<AtomicBool>,
+                          nodes_left: Arc<AtomicIsize>,
+                          start_depth: NumPlies) {
+    let start_time = Instant::now();
     let mut total_search_data = InnerData::one_node();
-    let mut curr_depth = NumPlies(1);
-
-    while !is_killed.load(Ordering::SeqCst) {
-        debug!("Starting depth limited search with depth = {} plies", curr_depth.0);
-        let mut temp_pos = pos.clone();
-        let result_opt = depth_limited_search(&mut temp_pos, search_moves,
-                                              curr_depth, &mut table, &is_killed);
-        if let None = result_opt { break; }
-        let result = result_opt.unwrap();
-
-        let (temp_best_score, temp_best_move, curr_search_data) = result;
-        best_score = temp_best_score;
-        best_move = temp_best_move;
-        total_search_data = total_search_data.combine(curr_search_data);
-
-        let _ = tx.send(Report { data: Data { nodes: total_search_data.nodes, depth: curr_depth },
-                                 score: best_score,
-                                 pv: vec![best_move] });
+    let mut curr_depth = start_depth;
+    let State { pos, mut history, param, .. } = state;
+    let mut heuristics = Heuristics::new();
+    let mut ctx = Context { table: &table,
+                            history: &mut history,
+                            heuristics: &mut heuristics,
+                            nodes_left: &nodes_left,
+                            is_killed: &is_killed,
+                            features: param.features };
+
+    // A mate in n moves is always found by a search of 2n-1 plies.
+    let max_depth = match (param.depth, param.mate) {
+        (depth, None) => depth,
+        (None, Some(mate)) => Some(NumPlies((2 * mate.0).saturating_sub(1))),
+        (Some(depth), Some(mate)) => Some(min(depth, NumPlies((2 * mate.0).saturating_sub(1)))),
+    };
+
+    'deepening: while !is_killed.load(Ordering::SeqCst) &&
+                      max_depth.is_none_or(|x| curr_depth <= x) {
+        // Each line is searched without the first moves of the better lines.
+        let mut remaining_moves = search_moves.to_vec();
+        for multi_pv in 1..(param.multi_pv + 1) {
+            if remaining_moves.is_empty() { break; }
+            debug!("Starting depth limited search with depth = {} plies, multipv = {}",
+                   curr_depth.0, multi_pv);
+            let mut temp_pos = pos.clone();
+            let curr_move_tx = tx.clone();
+            let mut curr_move_fn = |m: &Move, num: NumMoves| {
+                // Short searches would only flood the output.
+                if start_time.elapsed() >= Duration::from_secs(1) {
+                    let _ = curr_move_tx.send(Info::CurrMove(curr_depth, *m, num));
+                }
+            };
+            let result_opt = depth_limited_search(&mut temp_pos, &remaining_moves, curr_depth,
+                                                  &mut ctx, &mut curr_move_fn);
+            if let None = result_opt { break 'deepening; }
+            let result = result_opt.unwrap();
+
+            let (best_score, pv, curr_search_data) = result;
+            let seldepth = curr_search_data.seldepth;
+            total_search_data = total_search_data.combine(curr_search_data);
+            remaining_moves.retain(|x| *x != pv[0]);
+
+            let data = Data { nodes: total_search_data.nodes,
+                              depth: curr_depth,
+                              seldepth: seldepth,
+                              time: start_time.elapsed(),
+                              hashfull: table.hashfull() };
+            let _ = tx.send(Info::Report(Report { data: data,
+                                                  score: best_score,
+                                                  pv: pv,
+                                                  multi_pv: multi_pv }));
+
+            if multi_pv == 1 {
+                if let Some(mate) = param.mate {
+                    if best_score >= Score::WinIn(mate) {
+                        debug!("Found mate within {} moves", mate.0);
+                        break 'deepening;
+                    }
+                }
+            }
+        }
 
         curr_depth.0 += 1;
     }
@@ -1,12 +1,12 @@
-#![feature(mpsc_select)]
 
 #[macro_use]
 extern crate log;
 
 extern crate game;
 
-pub use types::{NumNodes, State, Param, Cmd, Report, BestMove};
+pub use types::{NumNodes, State, Param, Features, Cmd, Info, Report, Data, BestMove};
 pub use start::start;
+pub use transposition_table::TranspositionTable;
 
 mod types;
 
@@ -15,5 +15,6 @@ mod start;
 mod iterated_deepening;
 mod depth_limited_search;
 mod negamax;
+mod move_picker;
 
 mod transposition_table;
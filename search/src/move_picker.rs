@@ -0,0 +1,267 @@
+//! Orders the moves of a position so that the ones likely to be best are searched first.
+
+use std::mem;
+
+use game::{Position, Move, PackedMove, MoveList, ScoreUnit, PieceType};
+use game::{Pawn, King, Queen, Bishop, Knight, Rook};
+
+// Killer moves remembered for each ply.
+const NUM_KILLERS: usize = 2;
+
+// History scores are halved once one of them reaches this.
+const MAX_HISTORY: i32 = 1 << 20;
+
+/// What the search learned about quiet moves, kept by each search thread.
+pub struct Heuristics {
+    // Quiet moves that caused a beta cutoff at each ply, most recent first.
+    killers: Vec<[Option<PackedMove>; NUM_KILLERS]>,
+    // Indexed by the moved piece and the to square.
+    history: [[i32; 64]; 12],
+}
+impl Heuristics {
+    pub fn new() -> Heuristics {
+        Heuristics {
+            killers: Vec::new(),
+            history: [[0; 64]; 12],
+        }
+    }
+
+    fn killers(&self, ply: usize) -> &[Option<PackedMove>] {
+        self.killers.get(ply).map_or(&[], |x| &x[..])
+    }
+
+    fn history(&self, pos: &Position, m: &Move) -> i32 {
+        self.history[pos.moved_piece(m) as usize][m.to().to_id() as usize]
+    }
+
+    /// Called when the quiet move m caused a beta cutoff.
+    pub fn update(&mut self, pos: &Position, m: &Move, ply: usize, depth: u32) {
+        while self.killers.len() <= ply {
+            self.killers.push([None, None]);
+        }
+        let killers = &mut self.killers[ply];
+        let packed = m.pack();
+        if killers[0] != Some(packed) {
+            killers[1] = killers[0].take();
+            killers[0] = Some(packed);
+        }
+
+        let entry = &mut self.history[pos.moved_piece(m) as usize][m.to().to_id() as usize];
+        *entry += (depth * depth) as i32;
+        if *entry >= MAX_HISTORY {
+            for table in self.history.iter_mut() {
+                for x in table.iter_mut() {
+                    *x /= 2;
+                }
+            }
+        }
+    }
+}
+
+/// Which moves to pick.
+#[derive(Copy, Clone)]
+pub enum MoveKind<'a> {
+    // Only these moves, which must be legal.
+    Root(&'a [Move]),
+    All,
+    // Only captures and promotions that do not lose material.
+    GoodNoisy,
+    // Like GoodNoisy, and also the quiet moves that give check.
+    GoodNoisyAndChecks,
+}
+impl<'a> MoveKind<'a> {
+    /// Which moves to pick in the positions after these moves.
+    pub fn next(self) -> MoveKind<'a> {
+        match self {
+            MoveKind::GoodNoisyAndChecks => MoveKind::GoodNoisy,
+            x => x,
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+enum Stage {
+    TableMove,
+    GoodCaptures,
+    Killers,
+    Quiets,
+    BadCaptures,
+    QuietChecks,
+    Evasions,
+    Done,
+}
+
+/// Gives the moves in stages: the move from the transposition table,
+/// the captures that do not lose material ordered by MVV-LVA, the killer moves,
+/// the other quiet moves ordered by the history heuristic, and the other captures.
+///
+/// The moves of each stage are only generated once the previous stages run out,
+/// since a move from an earlier stage often causes a cutoff.
+///
+/// When in check, all the moves that get out of check are given after the table move,
+/// whatever the kind.
+pub struct MovePicker<'a> {
+    kind: MoveKind<'a>,
+    stage: Stage,
+    in_check: bool,
+    table_move: Option<Move>,
+    ply: usize,
+    // The moves of the current stage, with the best one last.
+    moves: Vec<Move>,
+    quiets: Vec<Move>,
+    bad_captures: Vec<Move>,
+}
+impl<'a> MovePicker<'a> {
+    pub fn new(kind: MoveKind<'a>, table_move: Option<Move>, ply: usize) -> MovePicker<'a> {
+        MovePicker {
+            kind: kind,
+            stage: Stage::TableMove,
+            in_check: false,
+            table_move: table_move,
+            ply: ply,
+            moves: Vec::new(),
+            quiets: Vec::new(),
+            bad_captures: Vec::new(),
+        }
+    }
+
+    pub fn next(&mut self, pos: &mut Position, heuristics: &Heuristics) -> Option<Move> {
+        loop {
+            if let Some(m) = self.moves.pop() {
+                return Some(m);
+            }
+            match self.stage {
+                Stage::TableMove => {
+                    self.in_check = pos.is_in_check();
+                    self.stage = if self.in_check { Stage::Evasions } else { Stage::GoodCaptures };
+                    if let Some(m) = self.table_move {
+                        if self.is_allowed(&m) && pos.is_legal(&m) {
+                            return Some(m);
+                        }
+                        self.table_move = None;
+                    }
+                },
+                Stage::GoodCaptures => {
+                    self.stage = match self.kind {
+                        MoveKind::GoodNoisy => Stage::Done,
+                        MoveKind::GoodNoisyAndChecks => Stage::QuietChecks,
+                        _ => Stage::Killers,
+                    };
+                    let mut noisy = MoveList::new();
+                    pos.generate_captures(&mut noisy);
+                    let mut good = Vec::new();
+                    for &m in noisy.iter() {
+                        if !self.is_new(&m) {
+                            continue;
+                        }
+                        if pos.see_ge(&m, ScoreUnit(0)) {
+                            good.push(m);
+                        } else {
+                            self.bad_captures.push(m);
+                        }
+                    }
+                    sort_by_key(&mut good, |m| mvv_lva(pos, m));
+                    self.moves = good;
+                },
+                Stage::Killers => {
+                    self.stage = Stage::Quiets;
+                    let mut all_quiets = MoveList::new();
+                    pos.generate_quiets(&mut all_quiets);
+                    let quiets: Vec<Move> = all_quiets.iter()
+                                                      .filter(|x| self.is_new(x))
+                                                      .cloned()
+                                                      .collect();
+                    self.quiets = quiets;
+                    // The most recent killer is searched first.
+                    for killer in heuristics.killers(self.ply).iter().rev() {
+                        if let Some(killer) = *killer {
+                            if let Some(idx) = self.quiets.iter().position(|x| x.pack() == killer) {
+                                let m = self.quiets.swap_remove(idx);
+                                self.moves.push(m);
+                            }
+                        }
+                    }
+                },
+                Stage::Quiets => {
+                    self.stage = Stage::BadCaptures;
+                    let mut quiets = mem::take(&mut self.quiets);
+                    sort_by_key(&mut quiets, |m| heuristics.history(pos, m));
+                    self.moves = quiets;
+                },
+                Stage::BadCaptures => {
+                    self.stage = Stage::Done;
+                    let mut bad = mem::take(&mut self.bad_captures);
+                    sort_by_key(&mut bad, |m| mvv_lva(pos, m));
+                    self.moves = bad;
+                },
+                Stage::QuietChecks => {
+                    self.stage = Stage::Done;
+                    let mut checks = MoveList::new();
+                    pos.generate_quiet_checks(&mut checks);
+                    // A quiet table move is not allowed, so it was not given already.
+                    self.moves = checks.iter().cloned().collect();
+                },
+                Stage::Evasions => {
+                    self.stage = Stage::Done;
+                    let mut all = MoveList::new();
+                    pos.generate_evasions(&mut all);
+                    let mut evasions: Vec<Move> = all.iter()
+                                                     .filter(|x| self.is_new(x))
+                                                     .cloned()
+                                                     .collect();
+                    // Captures first, then the other moves by history.
+                    sort_by_key(&mut evasions, |m| if m.is_noisy() {
+                        MAX_HISTORY + mvv_lva(pos, m)
+                    } else {
+                        heuristics.history(pos, m)
+                    });
+                    self.moves = evasions;
+                },
+                Stage::Done => return None,
+            }
+        }
+    }
+
+    fn is_allowed(&self, m: &Move) -> bool {
+        match self.kind {
+            MoveKind::Root(moves) => moves.contains(m),
+            MoveKind::All => true,
+            _ if self.in_check => true,
+            MoveKind::GoodNoisy | MoveKind::GoodNoisyAndChecks => m.is_noisy(),
+        }
+    }
+
+    // Whether m is allowed and was not already given as the table move.
+    fn is_new(&self, m: &Move) -> bool {
+        self.is_allowed(m) && self.table_move.as_ref() != Some(m)
+    }
+}
+
+// Sorts so that the moves with the highest key are last.
+fn sort_by_key<F>(moves: &mut Vec<Move>, mut f: F) where F: FnMut(&Move) -> i32 {
+    let mut keyed: Vec<(i32, Move)> = moves.drain(..).map(|m| (f(&m), m)).collect();
+    keyed.sort_by_key(|x| x.0);
+    moves.extend(keyed.into_iter().map(|x| x.1));
+}
+
+// Most valuable victim first, then least valuable attacker first.
+fn mvv_lva(pos: &Position, m: &Move) -> i32 {
+    let victim = if m.is_en_passant() {
+        value_of(Pawn)
+    } else {
+        m.capture_normal().map_or(0, |x| value_of(x.piece_type()))
+    };
+    let promote = m.promote().map_or(0, |x| value_of(x) - value_of(Pawn));
+    (victim + promote) * 16 - value_of(pos.moved_piece(m).piece_type())
+}
+
+fn value_of(piece_type: PieceType) -> i32 {
+    match piece_type {
+        Pawn   => 1,
+        Knight => 3,
+        Bishop => 3,
+        Rook   => 5,
+        Queen  => 9,
+        King   => 10,
+    }
+}
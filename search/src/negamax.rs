@@ -1,10 +1,11 @@
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicIsize, Ordering};
 use std::cmp::max;
 
-use game::{Position, Move, Score, ScoreUnit, NumPlies};
-use types::InnerData;
+use game::{Position, Move, Score, ScoreUnit, NumPlies, NumMoves, ZobristHash};
+use types::{InnerData, Features};
 
 use transposition_table::TranspositionTable;
+use move_picker::{MovePicker, MoveKind, Heuristics};
 
 #[derive(Copy, Clone, Debug)]
 pub enum Bound {
@@ -36,136 +37,227 @@ impl Bound {
 pub struct Param {
     pub eval_depth: Option<NumPlies>,
     pub table_depth: NumPlies,
+    // False at the root, where the moves searched may be restricted,
+    // so a score from the table might belong to a move that was not searched.
+    pub table_cutoff: bool,
+    // Distance from the root.
+    pub ply: usize,
+    // False right after a null move, so that two are never made in a row.
+    pub allow_null: bool,
+    // Positions in ctx.history before this index do not count as repetitions,
+    // since they came before a null move.
+    pub history_start: usize,
 }
 
+// What all nodes searched by one thread share, besides the position.
+pub struct Context<'a> {
+    pub table: &'a TranspositionTable,
+    pub history: &'a mut Vec<ZobristHash>,
+    pub heuristics: &'a mut Heuristics,
+    pub nodes_left: &'a AtomicIsize,
+    pub is_killed: &'a AtomicBool,
+    pub features: Features,
+}
+
+// Per ply of remaining depth.
+const FUTILITY_MARGIN: i32 = 120;
+
 pub fn negamax_root(pos: &mut Position,
                     alpha: Option<Score>,
                     beta: Option<Score>,
                     depth: NumPlies,
-                    table: &mut TranspositionTable,
-                    is_killed: &AtomicBool,
-                    search_moves: &[Move]) -> (Bound, Option<Move>, InnerData) {
+                    ctx: &mut Context,
+                    search_moves: &[Move],
+                    curr_move_fn: &mut FnMut(&Move, NumMoves)) -> (Bound, Vec<Move>, InnerData) {
     let next_depth = NumPlies(depth.0 - 1);
     let param = Param { eval_depth: Some(NumPlies(1)),
-                        table_depth: depth };
-    negamax_generic(pos, alpha, beta, param, table, is_killed,
-                    &mut |_| Box::new(search_moves.to_vec().into_iter()),
-                    &mut |inner_pos, inner_alpha, inner_beta, inner_table| {
+                        table_depth: depth,
+                        table_cutoff: false,
+                        ply: 0,
+                        allow_null: true,
+                        history_start: 0 };
+    negamax_generic(pos, alpha, beta, param, ctx,
+                    MoveKind::Root(search_moves),
+                    &mut |inner_pos, inner_alpha, inner_beta, inner_ctx| {
                         let inner_param = Param {
                             eval_depth: Some(next_depth),
                             table_depth: next_depth,
+                            table_cutoff: true,
+                            ply: 1,
+                            allow_null: true,
+                            history_start: 0,
                         };
-                        let (bound, _, data) =
-                            negamax_inner(inner_pos, inner_alpha, inner_beta,
-                                          inner_param, inner_table, is_killed);
-                        (bound, data)
+                        negamax_inner(inner_pos, inner_alpha, inner_beta, inner_param, inner_ctx)
                     },
-                    &mut |_| None)
+                    &mut |_| None,
+                    &mut |m, n| curr_move_fn(m, n))
 }
 
 fn negamax_inner(pos: &mut Position,
                  alpha: Option<Score>,
                  beta: Option<Score>,
                  param: Param,
-                 table: &mut TranspositionTable,
-                 is_killed: &AtomicBool) -> (Bound, Option<Move>, InnerData) {
-    negamax_generic(pos, alpha, beta, param, table, is_killed,
-                    &mut |x| Box::new(x.legal_iter()),
-                    &mut |x, inner_alpha, inner_beta, table| {
+                 ctx: &mut Context) -> (Bound, Vec<Move>, InnerData) {
+    let (ply, history_start) = (param.ply, param.history_start);
+    negamax_generic(pos, alpha, beta, param, ctx,
+                    MoveKind::All,
+                    &mut |x, inner_alpha, inner_beta, inner_ctx| {
                         let quiescence_param = Param {
                             eval_depth: None,
                             table_depth: NumPlies(0),
+                            table_cutoff: true,
+                            ply: ply,
+                            allow_null: false,
+                            history_start: history_start,
                         };
                         let (bound, _, data) = quiescence(x, inner_alpha, inner_beta,
-                                                          quiescence_param, table, is_killed);
-                        (bound, data)
+                                                          quiescence_param, inner_ctx);
+                        // Captures in quiescence are not part of the reported PV.
+                        (bound, Vec::new(), data)
                     },
-                    &mut |_| None)
+                    &mut |_| None,
+                    &mut |_, _| {})
 }
 
 fn quiescence(pos: &mut Position,
               alpha: Option<Score>,
               beta: Option<Score>,
               param: Param,
-              table: &mut TranspositionTable,
-              is_killed: &AtomicBool) -> (Bound, Option<Move>, InnerData) {
-    negamax_generic(pos, alpha, beta, param, table, is_killed,
-                    &mut |x| Box::new(x.legal_noisy_iter()),
+              ctx: &mut Context) -> (Bound, Vec<Move>, InnerData) {
+    // Checks are only searched at the first ply, which keeps the search from
+    // running into long series of checks.
+    negamax_generic(pos, alpha, beta, param, ctx,
+                    MoveKind::GoodNoisyAndChecks,
                     &mut |x, _, _, _|
-                        (Bound::Exact(x.eval()), InnerData::one_node()),
-                    &mut |x| Some(x.eval()))
+                        (Bound::Exact(x.eval()), Vec::new(), InnerData::one_node()),
+                    // When in check, every way out of it is searched instead.
+                    &mut |x| if x.is_in_check() { None } else { Some(x.eval()) },
+                    &mut |_, _| {})
 }
 
-// TODO somehow eliminate the Box
-fn negamax_generic<F, G, H>(pos: &mut Position,
+fn negamax_generic<G, H, I>(pos: &mut Position,
                             alpha: Option<Score>,
                             beta: Option<Score>,
                             param: Param,
-                            table: &mut TranspositionTable,
-                            is_killed: &AtomicBool,
-                            move_gen_fn: &mut F,
+                            ctx: &mut Context,
+                            move_kind: MoveKind,
                             eval_fn: &mut G,
-                            stand_pat_fn: &mut H) -> (Bound, Option<Move>, InnerData) where
-for<'a> F: FnMut(&'a Position) -> Box<Iterator<Item = Move> + 'a>,
+                            stand_pat_fn: &mut H,
+                            curr_move_fn: &mut I) -> (Bound, Vec<Move>, InnerData) where
 for<'b> G: FnMut(&'b mut Position, Option<Score>, Option<Score>,
-                 &mut TranspositionTable) -> (Bound, InnerData),
-for<'c> H: FnMut(&'c mut Position) -> Option<Score> {
-    if is_killed.load(Ordering::Relaxed) {
-        return (Bound::Exact(Score::Value(ScoreUnit(0))), None, InnerData::one_node());
+                 &mut Context) -> (Bound, Vec<Move>, InnerData),
+for<'c> H: FnMut(&'c mut Position) -> Option<Score>,
+for<'d> I: FnMut(&'d Move, NumMoves) {
+    if ctx.nodes_left.fetch_sub(1, Ordering::Relaxed) <= 0 {
+        ctx.is_killed.store(true, Ordering::Relaxed);
+    }
+    if ctx.is_killed.load(Ordering::Relaxed) {
+        return (Bound::Exact(Score::Value(ScoreUnit(0))), Vec::new(), InnerData::one_node());
     }
 
     let mut table_best_move_opt = None;
-    if let Some(data_ref) = table.get(pos) {
-        table_best_move_opt = data_ref.best_move_opt.clone();
-        if data_ref.depth >= param.table_depth {
+    if let Some(data_ref) = ctx.table.get(pos) {
+        table_best_move_opt = data_ref.best_move_opt;
+        if param.table_cutoff && data_ref.depth >= param.table_depth {
             let table_bound = data_ref.bound;
+            // The table move is only pseudo-legal, and may be illegal after a key collision,
+            // so it is checked before it becomes the PV.
+            let mut table_pv = || -> Vec<Move> {
+                table_best_move_opt.into_iter().filter(|m| pos.is_legal(m)).collect()
+            };
             let lower_than_alpha = alpha.is_some() &&
                                    !table_bound.is_lower() &&
                                    table_bound.as_score() <= alpha.unwrap();
             if lower_than_alpha {
-                return (Bound::Upper(alpha.unwrap()), table_best_move_opt, InnerData::one_node());
+                return (Bound::Upper(alpha.unwrap()), table_pv(), InnerData::one_node());
             }
             let higher_than_beta = beta.is_some() &&
                                    !table_bound.is_upper() &&
                                    table_bound.as_score() >= beta.unwrap();
             if higher_than_beta {
-                return (Bound::Lower(beta.unwrap()), table_best_move_opt, InnerData::one_node());
+                return (Bound::Lower(beta.unwrap()), table_pv(), InnerData::one_node());
             }
             if table_bound.is_exact() {
-                return (table_bound, table_best_move_opt, InnerData::one_node());
+                return (table_bound, table_pv(), InnerData::one_node());
             }
         }
     }
     let table_best_move_opt = table_best_move_opt;
 
     if param.eval_depth == Some(NumPlies(0)) {
-        let (bound, data) = eval_fn(pos, alpha, beta, table);
-        table.set(pos, NumPlies(0), None, bound);
-        return (bound, None, data);
+        let (bound, pv, data) = eval_fn(pos, alpha, beta, ctx);
+        ctx.table.set(pos, NumPlies(0), None, bound);
+        return (bound, pv, data);
     }
 
-    let (has_legal, score_opt, best_move_opt, data):
-        (bool, Option<Score>, Option<Move>, InnerData) = (|| {
-        let temp = pos.clone();
-        let move_iter: Box<Iterator<Item = Move>> = {
-            let normal_iter = move_gen_fn(&temp);
-            if let Some(ref table_move) = table_best_move_opt {
-                let is_table_move_valid = move_gen_fn(&temp).any(|y| y == *table_move);
-                if is_table_move_valid {
-                    Box::new(Some(
-                        table_move.clone()).into_iter()
-                                  .chain(normal_iter.filter(move |x| *x != *table_move)))
-                } else {
-                    normal_iter
-                }
-            } else {
-                normal_iter
+    // Pruning, reductions and extensions are only done away from the root
+    // and outside quiescence search.
+    let depth = param.eval_depth.map_or(0, |x| x.0);
+    let features = ctx.features;
+    let is_main = param.ply > 0 && depth > 0;
+    let in_check = is_main && pos.is_in_check();
+    let static_eval = if is_main && !in_check {
+        match pos.eval() {
+            Score::Value(val) => Some(val),
+            _ => None,
+        }
+    } else {
+        None
+    };
+
+    if let (Some(eval), Some(Score::Value(beta_val))) = (static_eval, beta) {
+        // Reverse futility pruning: far enough above beta that a shallow search
+        // is not going to bring the score back down.
+        if features.futility && depth <= 3 &&
+           eval - ScoreUnit(FUTILITY_MARGIN * depth as i32) >= beta_val {
+            return (Bound::Lower(beta.unwrap()), Vec::new(), InnerData::one_node());
+        }
+
+        // Null move pruning: if passing still keeps the score above beta, so should the
+        // best move. This does not hold in zugzwang, which is common without pieces.
+        let c = pos.side_to_move();
+        if features.null_move && param.allow_null && depth >= 3 && eval >= beta_val &&
+           pos.has_non_pawn_material(c) {
+            let reduction = if depth > 6 { 3 } else { 2 };
+            let null_param = Param {
+                eval_depth: Some(NumPlies(depth.saturating_sub(1 + reduction))),
+                table_depth: NumPlies(param.table_depth.0.saturating_sub(1 + reduction)),
+                table_cutoff: true,
+                ply: param.ply + 1,
+                allow_null: false,
+                // A repetition across the pass would not be a real one.
+                history_start: ctx.history.len() + 1,
+            };
+            let null_alpha = Score::Value(-beta_val);
+            let null_beta = Score::Value(-(beta_val - ScoreUnit(1)));
+            ctx.history.push(pos.hash());
+            let (null_bound, _, null_data) = pos.with_null_move(|new_pos| {
+                negamax_generic(new_pos, Some(null_alpha), Some(null_beta), null_param, ctx,
+                                move_kind, eval_fn, stand_pat_fn, curr_move_fn)
+            });
+            ctx.history.pop();
+            if null_bound.as_score().increment() >= beta.unwrap() {
+                return (Bound::Lower(beta.unwrap()), Vec::new(), null_data.increment());
             }
-        };
+        }
+    }
+
+    // Futility pruning: so far below alpha that quiet moves are unlikely to help.
+    let is_futile = match (static_eval, alpha) {
+        (Some(eval), Some(Score::Value(alpha_val))) =>
+            features.futility && depth <= 2 &&
+            eval + ScoreUnit(FUTILITY_MARGIN * depth as i32) <= alpha_val,
+        _ => false,
+    };
+
+    let (has_legal, score_opt, pv, data):
+        (bool, Option<Score>, Vec<Move>, InnerData) = (|| {
+        let mut picker = MovePicker::new(move_kind, table_best_move_opt, param.ply);
 
         let mut has_legal = false;
         let mut prev_score_opt: Option<Score> = alpha;
-        let mut prev_best_move_opt: Option<Move> = None;
+        // The best move followed by the PV of the position after it.
+        let mut prev_pv: Vec<Move> = Vec::new();
 
         if let Some(stand_pat_score) = stand_pat_fn(pos) {
             let new_score = match prev_score_opt {
@@ -178,60 +270,132 @@ for<'c> H: FnMut(&'c mut Position) -> Option<Score> {
 
             if let Some(beta_val) = beta {
                 if new_score >= beta_val {
-                    return (true, beta, None, InnerData::one_node());
+                    return (true, beta, Vec::new(), InnerData::one_node());
                 }
             }
         }
 
         let mut prev_data = InnerData::one_node();
 
-        for curr_move in move_iter {
+        let mut num_moves = 0;
+        while let Some(curr_move) = picker.next(pos, ctx.heuristics) {
+            num_moves += 1;
+            curr_move_fn(&curr_move, NumMoves(num_moves));
 
             let new_alpha = beta.map(|x| x.decrement());
             let new_beta = prev_score_opt.map(|x| x.decrement());
-            let new_param = Param {
-                eval_depth: param.eval_depth.map(|x| NumPlies(x.0 - 1)),
-                table_depth: NumPlies(param.table_depth.0.saturating_sub(1)),
+            ctx.history.push(pos.hash());
+            let result_opt = pos.with_move(&curr_move, |new_pos| {
+                // Any repetition inside the search is scored as a draw,
+                // since the side that can claim it would do so.
+                if new_pos.repetition_count(&ctx.history[param.history_start..]) >= 1 ||
+                   new_pos.is_draw_by_fifty_move_rule() {
+                    return Some((Bound::Exact(Score::Value(ScoreUnit(0))), Vec::new(),
+                                 InnerData::one_node()));
+                }
+
+                let gives_check = is_main && new_pos.is_in_check();
+                let is_quiet = curr_move.is_quiet() && !gives_check;
+                if is_futile && num_moves > 1 && is_quiet {
+                    return None;
+                }
+                let extension = if features.check_extensions && gives_check { 1 } else { 0 };
+                // Late move reductions: quiet moves ordered late are rarely best.
+                let reduction = if features.lmr && is_main && depth >= 3 && num_moves > 3 &&
+                                   is_quiet && !in_check {
+                    if num_moves > 8 { 2 } else { 1 }
+                } else {
+                    0
+                };
+
+                let mut search = |new_pos: &mut Position,
+                                  child_alpha: Option<Score>,
+                                  child_beta: Option<Score>,
+                                  reduction: u32| {
+                    let new_param = Param {
+                        eval_depth: param.eval_depth.map(|x| {
+                            NumPlies((x.0 + extension).saturating_sub(1 + reduction))
+                        }),
+                        table_depth: NumPlies((param.table_depth.0 + extension)
+                                              .saturating_sub(1 + reduction)),
+                        table_cutoff: true,
+                        ply: param.ply + 1,
+                        allow_null: true,
+                        history_start: param.history_start,
+                    };
+                    negamax_generic(new_pos,
+                                    child_alpha,
+                                    child_beta,
+                                    new_param,
+                                    ctx,
+                                    move_kind.next(),
+                                    eval_fn,
+                                    stand_pat_fn,
+                                    curr_move_fn)
+                };
+
+                // Principal variation search: after the first move, only check whether
+                // each move beats alpha with a null window, and search it again with
+                // the full window if it does.
+                if let (true, Some(Score::Value(prev_val))) = (num_moves > 1, prev_score_opt) {
+                    let prev_score = Score::Value(prev_val);
+                    let null_alpha = Some(Score::Value(-(prev_val + ScoreUnit(1))));
+                    let null_beta = Some(Score::Value(-prev_val));
+                    // Then the full window is already a null window.
+                    let is_null_window = beta == Some(Score::Value(prev_val + ScoreUnit(1)));
+                    if reduction > 0 {
+                        let ans = search(new_pos, null_alpha, null_beta, reduction);
+                        if ans.0.as_score().increment() <= prev_score {
+                            return Some(ans);
+                        }
+                    }
+                    if features.pvs && !is_null_window {
+                        let ans = search(new_pos, null_alpha, null_beta, 0);
+                        let score = ans.0.as_score().increment();
+                        if score <= prev_score || beta.is_some_and(|x| score >= x) {
+                            return Some(ans);
+                        }
+                    }
+                }
+                Some(search(new_pos, new_alpha, new_beta, 0))
+            });
+            ctx.history.pop();
+            let (temp_bound, temp_pv, temp_data) = match result_opt {
+                Some(val) => val,
+                // Pruned by futility
+                None => continue,
             };
-            let (temp_bound, _, temp_data) = pos.with_move(&curr_move, |new_pos|
-                negamax_generic(new_pos,
-                                new_alpha,
-                                new_beta,
-                                new_param,
-                                table,
-                                is_killed,
-                                move_gen_fn,
-                                eval_fn,
-                                stand_pat_fn));
             let curr_score = temp_bound.as_score().increment();
             let curr_data = temp_data.increment();
 
-            let (new_score, new_best_move_opt) = match prev_score_opt {
-                None => (curr_score, Some(curr_move)),
-                Some(prev_score) => {
-                    if curr_score <= prev_score {
-                        (prev_score, prev_best_move_opt)
-                    } else {
-                        (curr_score, Some(curr_move))
-                    }
-                }
+            let is_improved = prev_score_opt.is_none_or(|prev_score| curr_score > prev_score);
+            let (new_score, new_pv) = if is_improved {
+                let mut new_pv = vec![curr_move];
+                new_pv.extend(temp_pv);
+                (curr_score, new_pv)
+            } else {
+                (prev_score_opt.unwrap(), prev_pv)
             };
             let new_data = prev_data.combine(curr_data);
 
             has_legal = true;
             prev_score_opt = Some(new_score);
-            prev_best_move_opt = new_best_move_opt;
+            prev_pv = new_pv;
             prev_data = new_data;
 
             if let Some(beta_val) = beta {
                 if new_score >= beta_val {
+                    // The quiet moves of quiescence search are only checks and evasions.
+                    if curr_move.is_quiet() && param.eval_depth.is_some() {
+                        ctx.heuristics.update(pos, &curr_move, param.ply, param.table_depth.0);
+                    }
                     prev_score_opt = beta;
                     break;
                 }
             }
         }
 
-        (has_legal, prev_score_opt, prev_best_move_opt, prev_data)
+        (has_legal, prev_score_opt, prev_pv, prev_data)
     })();
 
     if has_legal {
@@ -245,11 +409,11 @@ for<'c> H: FnMut(&'c mut Position) -> Option<Score> {
                 Bound::Exact(score)
             }
         };
-        table.set(pos, param.table_depth, best_move_opt.clone(), bound);
-        (bound, best_move_opt, data)
+        ctx.table.set(pos, param.table_depth, pv.first().cloned(), bound);
+        (bound, pv, data)
     } else {
-        let (bound, data) = eval_fn(pos, alpha, beta, table);
-        table.set(pos, param.table_depth, None, bound);
-        (bound, None, data)
+        let (bound, _, data) = eval_fn(pos, alpha, beta, ctx);
+        ctx.table.set(pos, param.table_depth, None, bound);
+        (bound, Vec::new(), data)
     }
 }
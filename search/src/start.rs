@@ -1,44 +1,33 @@
 use std::sync::mpsc::{Sender, Receiver, channel};
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicIsize, Ordering};
 use std::sync::Arc;
 use std::thread;
-use std::mem::size_of;
+use std::time::Duration;
+use std::cmp::min;
 
 use game::{Move, Score, ScoreUnit, NumPlies};
 
-use types::{State, Cmd, Data, Report, BestMove, NumNodes};
+use types::{State, Cmd, Data, Info, Report, BestMove, NumNodes};
 use iterated_deepening::iterated_deepening;
-use transposition_table::{self, TranspositionTable};
+use transposition_table::TranspositionTable;
 
-pub fn start(mut state: State, rx: Receiver<Cmd>,
-             tx: Sender<Report>) -> BestMove {
-    if state.param.ponder {
-        debug!("pondering, waiting for next command");
-        // Actually should ponder, but now just waits for our move.
-        for cmd in rx.iter() {
-            match cmd {
-                Cmd::SetDebug(val) => {
-                    // TODO set debug
-                    debug!("debug is now {:?}", val);
-                },
-                Cmd::PonderHit => {
-                    debug!("ponder hit when pondering");
-                    state.param.ponder = false;
-                    break;
-                },
-                Cmd::Stop => {
-                    debug!("stop from pondering");
-                    // TODO report stuff.about pondering and terminate.
-                    unimplemented!();
-                },
-            }
-        }
-        if state.param.ponder {
-            panic!("Sender hung up while pondering");
-        }
-        debug!("pondering finished");
-    }
+// Everything that start() waits for while the search is running.
+// Info and Finished are tagged with the index of the search thread.
+enum Event {
+    Cmd(Cmd),
+    Info(usize, Info),
+    Finished(usize),
+}
 
+/// Searches until Cmd::Stop is received, and returns the best move found.
+///
+/// The table is kept by the caller, so that it can be reused by the next search.
+///
+/// If the search reaches the depth, nodes or mate limit of `state.param` first,
+/// `on_finish` is called so that the caller can send Cmd::Stop,
+/// unless the search is infinite or still pondering.
+pub fn start<F>(state: State, table: Arc<TranspositionTable>, rx: Receiver<Cmd>,
+                tx: Sender<Info>, on_finish: F) -> BestMove where F: FnOnce() {
     let search_moves: Vec<(Move)> = {
         //let legal_moves_chan = receive_legal(state.pos.clone());
         let legal_moves = state.pos.legal_iter();
@@ -52,26 +41,71 @@ pub fn start(mut state: State, rx: Receiver<Cmd>,
     }
 
     // this is just a placeholder report
-    let mut last_report = Report { data: Data { nodes: NumNodes(0), depth: NumPlies(0) },
+    let mut last_report = Report { data: Data { nodes: NumNodes(0),
+                                                depth: NumPlies(0),
+                                                seldepth: NumPlies(0),
+                                                time: Duration::from_secs(0),
+                                                hashfull: 0 },
                                    score: Score::Value(ScoreUnit(0)),
-                                   pv: vec![search_moves[0].clone()] };
-    let table_capacity = state.param.hash_size /
-                         size_of::<Option<transposition_table::Data>>();
-    let table = TranspositionTable::with_capacity(table_capacity);
+                                   pv: vec![search_moves[0]],
+                                   multi_pv: 1 };
+    table.new_search();
 
-    let (search_tx, search_rx) = channel::<Report>();
+    let (event_tx, event_rx) = channel::<Event>();
     let is_killed = Arc::new(AtomicBool::new(false));
+    // Shared by all threads, so that the node limit applies to their sum.
+    let max_nodes = state.param.nodes.map_or(isize::MAX, |x| {
+        min(x.0, isize::MAX as u64) as isize
+    });
+    let nodes_left = Arc::new(AtomicIsize::new(max_nodes));
 
-    let temp_is_killed = is_killed.clone();
+    let temp_event_tx = event_tx.clone();
+    thread::spawn(move || {
+        for cmd in rx.iter() {
+            if temp_event_tx.send(Event::Cmd(cmd)).is_err() { break; }
+        }
+    });
+
+    // While pondering, the search runs on the position after the expected
+    // opponent move, but must not stop by itself before Cmd::PonderHit.
+    let mut is_pondering = state.param.ponder;
+    let is_infinite = state.param.infinite;
+    let num_threads = state.param.threads;
 
-    debug!("Starting iterated deepening search");
-    thread::spawn(move ||
-        iterated_deepening(state.pos, &search_moves, table, search_tx, temp_is_killed));
+    debug!("Starting iterated deepening search with {} threads", num_threads);
+    for id in 0..num_threads {
+        let (search_tx, search_rx) = channel::<Info>();
+        let state = state.clone();
+        let search_moves = search_moves.clone();
+        let table = table.clone();
+        let is_killed = is_killed.clone();
+        let nodes_left = nodes_left.clone();
+        // Helper threads start one ply deeper on every other thread,
+        // so that they do not all search the same tree in lockstep.
+        let start_depth = NumPlies(1 + (id % 2) as u32);
+        thread::spawn(move ||
+            iterated_deepening(state, &search_moves, table, search_tx, is_killed, nodes_left,
+                               start_depth));
+
+        // Reports stop coming once iterated_deepening returns.
+        let event_tx = event_tx.clone();
+        thread::spawn(move || {
+            for info in search_rx.iter() {
+                if event_tx.send(Event::Info(id, info)).is_err() { return; }
+            }
+            let _ = event_tx.send(Event::Finished(id));
+        });
+    }
+    drop(event_tx);
 
-    loop {
-        select! {
-            val = rx.recv() => {
-                let cmd = val.ok().expect("Sender hung up while calculating");
+    // The thread and depth of the line 1 report that was last sent.
+    // Other lines are only sent if they come from the same iteration.
+    let mut best_thread_depth = (0, NumPlies(0));
+    let mut num_finished = 0;
+    let mut on_finish = Some(on_finish);
+    for event in event_rx.iter() {
+        match event {
+            Event::Cmd(cmd) => {
                 debug!("received command {:?}", cmd);
                 match cmd {
                     Cmd::SetDebug(val) => {
@@ -79,31 +113,64 @@ pub fn start(mut state: State, rx: Receiver<Cmd>,
                         debug!("debug is now {:?}", val);
                     },
                     Cmd::PonderHit => {
-                        debug!("ponder hit when not pondering (ignored)");
-                        // Ignore this cmd
+                        if !is_pondering {
+                            debug!("ponder hit when not pondering (ignored)");
+                            continue;
+                        }
+                        debug!("ponder hit, continuing the same search");
+                        is_pondering = false;
+                        if num_finished == num_threads && !is_infinite {
+                            if let Some(f) = on_finish.take() {
+                                f();
+                            }
+                        }
                     },
                     Cmd::Stop => {
-
                         debug!("killing search");
                         is_killed.store(true, Ordering::SeqCst);
 
                         debug!("reporting result");
-                        tx.send(last_report.clone()).unwrap();
+                        tx.send(Info::Report(last_report.clone())).unwrap();
 
                         debug!("search stopping");
-                        return BestMove(last_report.pv[0].clone(), None);
+                        return BestMove(last_report.pv[0], last_report.pv.get(1).cloned());
+                    },
+                }
+            },
+            Event::Info(id, Info::Report(mut report)) => {
+                debug!("receiving result from iterated_deepening in thread {}", id);
+                let depth = report.data.depth;
+                let is_best = if report.multi_pv == 1 {
+                    depth > best_thread_depth.1
+                } else {
+                    (id, depth) == best_thread_depth
+                };
+                if !is_best { continue; }
+                report.data.nodes = NumNodes((max_nodes - nodes_left.load(Ordering::Relaxed))
+                                             as u64);
+                if report.multi_pv == 1 {
+                    best_thread_depth = (id, depth);
+                    last_report = report.clone();
+                }
+                tx.send(Info::Report(report)).unwrap();
+            },
+            Event::Info(id, info) => {
+                // Other threads would repeat the same root moves.
+                if id == 0 {
+                    tx.send(info).unwrap();
+                }
+            },
+            Event::Finished(id) => {
+                debug!("iterated_deepening in thread {} reached its limits", id);
+                // The result is still only returned on Cmd::Stop.
+                num_finished += 1;
+                if num_finished == num_threads && !is_pondering && !is_infinite {
+                    if let Some(f) = on_finish.take() {
+                        f();
                     }
                 }
             },
-            search_res = search_rx.recv() => {
-                debug!("receiving result from iterated_deepening");
-
-                last_report = search_res.ok()
-                                        .expect("iterated_deepening unexpectedly dropped Sender");
-
-                tx.send(last_report.clone()).unwrap();
-            }
         }
     }
+    panic!("Sender hung up while calculating");
 }
-
@@ -1,43 +1,221 @@
-use game::{Position, Move, ZobristHash, NumPlies};
+use std::cmp::{min, max};
+use std::mem::size_of;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use game::{Position, Move, PackedMove, Score, ScoreUnit, NumMoves, NumPlies};
 
 use negamax::Bound;
 
+// Buckets are split between this many locks, so that search threads
+// rarely wait for each other.
+const NUM_STRIPES: usize = 1024;
+
+const BUCKET_SIZE: usize = 4;
+
+// Generations wrap around after this many searches.
+const NUM_GENERATIONS: usize = 16;
+
 #[derive(Clone, Debug)]
 pub struct Data {
-    pub hash: ZobristHash,
     pub depth: NumPlies,
     pub bound: Bound,
     pub best_move_opt: Option<Move>,
 }
 
-pub struct TranspositionTable(Vec<Option<Data>>);
+// flags holds the bound type in bits 0-1 (0 for an empty entry),
+// the score type in bits 2-3, and the generation in bits 4-7.
+#[derive(Copy, Clone, Debug)]
+struct Entry {
+    key: u32,
+    score: i32,
+    best_move: Option<PackedMove>,
+    depth: u8,
+    flags: u8,
+}
+impl Entry {
+    fn empty() -> Entry {
+        Entry { key: 0, score: 0, best_move: None, depth: 0, flags: 0 }
+    }
+    fn is_empty(&self) -> bool { self.flags & 3 == 0 }
+    fn generation(&self) -> usize { (self.flags >> 4) as usize }
+
+    fn new(key: u32, depth: NumPlies, best_move: Option<PackedMove>, bound: Bound,
+           generation: usize) -> Entry {
+        let (bound_bits, score) = match bound {
+            Bound::Exact(x) => (1, x),
+            Bound::Lower(x) => (2, x),
+            Bound::Upper(x) => (3, x),
+        };
+        let (score_bits, score_val) = match score {
+            Score::Value(ScoreUnit(x)) => (0, x),
+            Score::WinIn(NumMoves(x)) => (1, x as i32),
+            Score::LoseIn(NumMoves(x)) => (2, x as i32),
+        };
+        Entry {
+            key: key,
+            score: score_val,
+            best_move: best_move,
+            depth: min(depth.0, 255) as u8,
+            flags: bound_bits | (score_bits << 2) | ((generation as u8) << 4),
+        }
+    }
+
+    fn bound(&self) -> Bound {
+        let score = match (self.flags >> 2) & 3 {
+            0 => Score::Value(ScoreUnit(self.score)),
+            1 => Score::WinIn(NumMoves(self.score as u32)),
+            _ => Score::LoseIn(NumMoves(self.score as u32)),
+        };
+        match self.flags & 3 {
+            1 => Bound::Exact(score),
+            2 => Bound::Lower(score),
+            _ => Bound::Upper(score),
+        }
+    }
+}
+
+type Bucket = [Entry; BUCKET_SIZE];
+
+// Shared between all search threads.
+pub struct TranspositionTable {
+    stripes: Vec<Mutex<Vec<Bucket>>>,
+    // Both are powers of two.
+    num_buckets: usize,
+    num_stripes: usize,
+    generation: AtomicUsize,
+}
 impl TranspositionTable {
-    pub fn with_capacity(capacity: usize) -> Self {
-        TranspositionTable(vec![None; capacity])
-    }
-    pub fn get<'a>(&'a self, pos: &Position) -> Option<&'a Data> {
-        let hash = pos.hash();
-        let idx = (hash.0 % (self.0.len() as u64)) as usize;
-        self.0[idx].as_ref().and_then(|x| {
-            let is_correct_pos = x.hash == hash &&
-                                 x.best_move_opt.as_ref()
-                                  .map_or(true, |y| pos.legal_iter().any(|z| *y == z));
-            if is_correct_pos { Some(x) } else { None }
+    /// Uses at most `size` bytes, rounded down to a power of two number of buckets.
+    pub fn with_size(size: usize) -> Self {
+        TranspositionTable::try_with_size(size).expect("cannot allocate transposition table")
+    }
+
+    /// Like with_size(), but returns None if the memory cannot be allocated.
+    pub fn try_with_size(size: usize) -> Option<Self> {
+        let max_buckets = max(size / size_of::<Bucket>(), 1);
+        let mut num_buckets = 1;
+        while num_buckets * 2 <= max_buckets {
+            num_buckets *= 2;
+        }
+        let num_stripes = min(num_buckets, NUM_STRIPES);
+        let stripe_len = num_buckets / num_stripes;
+        let mut stripes = Vec::new();
+        if stripes.try_reserve_exact(num_stripes).is_err() {
+            return None;
+        }
+        for _ in 0..num_stripes {
+            let mut stripe = Vec::new();
+            if stripe.try_reserve_exact(stripe_len).is_err() {
+                return None;
+            }
+            stripe.resize(stripe_len, [Entry::empty(); BUCKET_SIZE]);
+            stripes.push(Mutex::new(stripe));
+        }
+        Some(TranspositionTable {
+            stripes: stripes,
+            num_buckets: num_buckets,
+            num_stripes: num_stripes,
+            generation: AtomicUsize::new(0),
         })
     }
-    // TODO implement a better replacement scheme
-    pub fn set(&mut self,
+
+    pub fn num_entries(&self) -> usize {
+        self.num_buckets * BUCKET_SIZE
+    }
+
+    /// Empties all entries.
+    pub fn clear(&self) {
+        for stripe in &self.stripes {
+            for bucket in stripe.lock().unwrap().iter_mut() {
+                *bucket = [Entry::empty(); BUCKET_SIZE];
+            }
+        }
+        self.generation.store(0, Ordering::SeqCst);
+    }
+
+    /// Should be called at the start of each search,
+    /// so that entries from older searches get replaced first.
+    pub fn new_search(&self) {
+        let generation = self.generation.load(Ordering::SeqCst);
+        self.generation.store((generation + 1) % NUM_GENERATIONS, Ordering::SeqCst);
+    }
+
+    fn with_bucket<T, F>(&self, idx: usize, f: F) -> T
+    where F: FnOnce(&mut Bucket) -> T {
+        let mut stripe = self.stripes[idx & (self.num_stripes - 1)].lock().unwrap();
+        f(&mut stripe[idx / self.num_stripes])
+    }
+
+    // The low bits of the hash choose the bucket, and the high bits are the key.
+    fn idx_key(&self, pos: &Position) -> (usize, u32) {
+        let hash = pos.hash().0;
+        ((hash as usize) & (self.num_buckets - 1), (hash >> 32) as u32)
+    }
+
+    pub fn get(&self, pos: &Position) -> Option<Data> {
+        let (idx, key) = self.idx_key(pos);
+        let entry_opt = self.with_bucket(idx, |bucket| {
+            bucket.iter().find(|x| !x.is_empty() && x.key == key).cloned()
+        });
+        entry_opt.and_then(|entry| {
+            let best_move_opt = match entry.best_move {
+                None => None,
+                // A move that cannot be played means that the key collided.
+                Some(packed) => match pos.unpack_move(packed) {
+                    Some(val) => Some(val),
+                    None => return None,
+                },
+            };
+            Some(Data {
+                depth: NumPlies(entry.depth as u32),
+                bound: entry.bound(),
+                best_move_opt: best_move_opt,
+            })
+        })
+    }
+
+    // Permill of entries used by the current search, estimated from the first thousand.
+    pub fn hashfull(&self) -> u32 {
+        let generation = self.generation.load(Ordering::Relaxed);
+        let num_sampled = min(self.num_buckets, 1000 / BUCKET_SIZE);
+        let used: usize = (0..num_sampled).map(|idx| self.with_bucket(idx, |bucket| {
+            bucket.iter().filter(|x| !x.is_empty() && x.generation() == generation).count()
+        })).sum();
+        (used * 1000 / (num_sampled * BUCKET_SIZE)) as u32
+    }
+
+    // Replaces the entry of the same position if there is one.
+    // Otherwise, replaces the entry that is shallowest after penalizing old entries.
+    pub fn set(&self,
                pos: &Position,
                depth: NumPlies,
                best_move_opt: Option<Move>,
                bound: Bound) {
-        let hash = pos.hash();
-        let idx = (hash.0 % (self.0.len() as u64)) as usize;
-        self.0[idx] = Some(Data {
-            hash: hash,
-            depth: depth,
-            best_move_opt: best_move_opt,
-            bound: bound
+        let (idx, key) = self.idx_key(pos);
+        let generation = self.generation.load(Ordering::Relaxed);
+        self.with_bucket(idx, |bucket| {
+            let replace_idx = match bucket.iter().position(|x| !x.is_empty() && x.key == key) {
+                Some(val) => val,
+                None => (0..BUCKET_SIZE).min_by_key(|&i| {
+                    let x = &bucket[i];
+                    if x.is_empty() {
+                        i32::MIN
+                    } else {
+                        let age = (generation + NUM_GENERATIONS - x.generation()) %
+                                  NUM_GENERATIONS;
+                        x.depth as i32 - 8 * age as i32
+                    }
+                }).unwrap(),
+            };
+            let old = bucket[replace_idx];
+            // Keep the old move rather than forgetting it.
+            let best_move = match best_move_opt {
+                Some(ref val) => Some(val.pack()),
+                None if !old.is_empty() && old.key == key => old.best_move,
+                None => None,
+            };
+            bucket[replace_idx] = Entry::new(key, depth, best_move, bound, generation);
         });
     }
 }
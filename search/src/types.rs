@@ -1,4 +1,7 @@
-use game::{Position, Move, Score, NumPlies, NumMoves};
+use std::time::Duration;
+use std::cmp::max;
+
+use game::{Position, Move, Score, NumPlies, NumMoves, ZobristHash};
 
 #[derive(PartialEq, Eq, PartialOrd, Ord, Copy, Clone, Debug)]
 pub struct NumNodes(pub u64);
@@ -9,27 +12,64 @@ pub struct State {
     pub pos: Position,
     pub prev_pos: Option<Position>,
     pub prev_move: Option<Move>,
+    // Hashes of all positions before pos in the game, oldest first.
+    pub history: Vec<ZobristHash>,
     pub param: Param,
 }
 
 #[derive(Clone, Debug)]
 pub struct Param {
     pub ponder: bool,
+    // Like pondering, the search does not stop by itself before Cmd::Stop.
+    pub infinite: bool,
     pub search_moves: Option<Vec<Move>>,
     pub depth: Option<NumPlies>,
     pub nodes: Option<NumNodes>,
     pub mate: Option<NumMoves>,
-    pub hash_size: usize,
+    // Number of best lines to search, each reported separately.
+    pub multi_pv: usize,
+    pub threads: usize,
+    pub features: Features,
 }
 impl Param {
-    pub fn new(hash_size: usize) -> Self {
+    pub fn new() -> Self {
         Param {
             ponder: false,
+            infinite: false,
             search_moves: None,
             depth: None,
             nodes: None,
             mate: None,
-            hash_size: hash_size,
+            multi_pv: 1,
+            threads: 1,
+            features: Features::all(),
+        }
+    }
+}
+impl Default for Param {
+    fn default() -> Self {
+        Param::new()
+    }
+}
+
+/// Search techniques that can be turned off, to compare the search with and without them.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub struct Features {
+    pub null_move: bool,
+    pub lmr: bool,
+    // Both futility pruning and reverse futility pruning.
+    pub futility: bool,
+    pub check_extensions: bool,
+    pub pvs: bool,
+}
+impl Features {
+    pub fn all() -> Self {
+        Features {
+            null_move: true,
+            lmr: true,
+            futility: true,
+            check_extensions: true,
+            pvs: true,
         }
     }
 }
@@ -44,30 +84,52 @@ pub enum Cmd {
 #[derive(PartialEq, Eq, Clone, Debug)]
 pub struct BestMove(pub Move, pub Option<Move>);
 
+#[derive(Clone, Debug)]
+pub enum Info {
+    Report(Report),
+    // The root move being searched at the given depth, and its 1-based index.
+    CurrMove(NumPlies, Move, NumMoves),
+}
+
 #[derive(Clone, Debug)]
 pub struct Report {
     pub data: Data,
     pub score: Score,
     pub pv: Vec<Move>,
+    // 1 for the best line, 2 for the second best line, and so on.
+    pub multi_pv: usize,
 }
 
 #[derive(Clone, Debug)]
 pub struct Data {
     pub nodes: NumNodes,
     pub depth: NumPlies,
+    pub seldepth: NumPlies,
+    pub time: Duration,
+    // Occupancy of the transposition table, in permill.
+    pub hashfull: u32,
 }
 
-// TODO put actual data here
 #[derive(Clone, Debug)]
 pub struct InnerData {
     pub nodes: NumNodes,
+    // The deepest ply reached below this node, including quiescence search.
+    pub seldepth: NumPlies,
 }
 impl InnerData {
-    pub fn one_node() -> InnerData { InnerData { nodes: NumNodes(1) } }
+    pub fn one_node() -> InnerData {
+        InnerData { nodes: NumNodes(1), seldepth: NumPlies(0) }
+    }
     pub fn combine(self, other: InnerData) -> InnerData {
-        InnerData { nodes: NumNodes(self.nodes.0 + other.nodes.0) }
+        InnerData {
+            nodes: NumNodes(self.nodes.0 + other.nodes.0),
+            seldepth: max(self.seldepth, other.seldepth),
+        }
     }
     pub fn increment(self) -> InnerData {
-        InnerData { nodes: NumNodes(self.nodes.0 + 1) }
+        InnerData {
+            nodes: NumNodes(self.nodes.0 + 1),
+            seldepth: NumPlies(self.seldepth.0 + 1),
+        }
     }
 }
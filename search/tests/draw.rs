@@ -0,0 +1,35 @@
+extern crate game;
+extern crate search;
+
+use std::sync::Arc;
+use std::sync::mpsc::channel;
+
+use game::{Position, Score, ScoreUnit, NumPlies};
+use search::{State, Param, Cmd, Info, TranspositionTable};
+
+// Searches the position to the given depth, and returns the score of the best line.
+fn search_score(fen: &str, depth: u32) -> Score {
+    let pos: Position = fen.parse().unwrap();
+    let mut param = Param::new();
+    param.depth = Some(NumPlies(depth));
+    let state = State { pos: pos, prev_pos: None, prev_move: None, history: Vec::new(),
+                        param: param };
+    let table = Arc::new(TranspositionTable::with_size(1 << 20));
+    let (cmd_tx, cmd_rx) = channel();
+    let (info_tx, info_rx) = channel();
+    search::start(state, table, cmd_rx, info_tx, move || cmd_tx.send(Cmd::Stop).unwrap());
+    info_rx.iter().filter_map(|x| match x {
+        Info::Report(report) => Some(report.score),
+        _ => None,
+    }).last().unwrap()
+}
+
+#[test]
+fn perpetual_check_under_null_move() {
+    // White is far ahead, so null moves are tried whenever white is not in check,
+    // but black draws with Qe1+ Kh2 Qh4+ Kg1 whatever white does.
+    let fen = "7k/6pp/b7/8/7q/8/PP1P2P1/RRB3K1 w - - 0 1";
+    for depth in 6..10 {
+        assert_eq!(search_score(fen, depth), Score::Value(ScoreUnit(0)), "depth {}", depth);
+    }
+}
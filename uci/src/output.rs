@@ -1,10 +1,13 @@
 use std::io::{Write, LineWriter};
 use std::sync::mpsc::{Receiver, SyncSender};
+use std::cmp::max;
 
 use search;
 
 use types::Response::{self, Info};
-use InfoParam::{self, Depth, NodesSearched, PrincipalVariation};
+use InfoParam::{self, Depth, SelDepth, TimeSearched, NodesSearched, NodesPerSec,
+                HashFull, PrincipalVariation, MultiPv, CurrMove, CurrMoveNumber};
+use types::{NumVariations, PerMill};
 
 pub fn format_output<W: Write>(output: W, rx: Receiver<Response>) {
     let mut output = LineWriter::new(output);
@@ -13,12 +16,29 @@ pub fn format_output<W: Write>(output: W, rx: Receiver<Response>) {
     }
 }
 
-pub fn engine_response_output(rx: Receiver<search::Report>, tx: SyncSender<Response>) {
-    for search::Report { data, score, pv } in rx.iter() {
-        tx.send(Info(vec![Depth(data.depth), NodesSearched(data.nodes)])).unwrap();
-        tx.send(Info(vec![InfoParam::Score(None, score),
-                          PrincipalVariation(pv)]
-                    )).unwrap();
-
+pub fn engine_response_output(rx: Receiver<search::Info>, tx: SyncSender<Response>) {
+    for info in rx.iter() {
+        match info {
+            search::Info::Report(search::Report { data, score, pv, multi_pv }) => {
+                let millis = data.time.as_secs() * 1000 + data.time.subsec_millis() as u64;
+                let nps = data.nodes.0 * 1000 / max(millis, 1);
+                tx.send(Info(vec![Depth(data.depth),
+                                  SelDepth(data.seldepth),
+                                  MultiPv(NumVariations(multi_pv as u32)),
+                                  InfoParam::Score(None, score),
+                                  NodesSearched(data.nodes),
+                                  NodesPerSec(nps),
+                                  HashFull(PerMill(data.hashfull)),
+                                  TimeSearched(data.time),
+                                  PrincipalVariation(pv)]
+                            )).unwrap();
+            },
+            search::Info::CurrMove(depth, curr_move, num) => {
+                tx.send(Info(vec![Depth(depth),
+                                  CurrMove(curr_move),
+                                  CurrMoveNumber(num)]
+                            )).unwrap();
+            },
+        }
     }
 }
@@ -143,7 +143,13 @@ where I: Iterator<Item = &'a str> {
         words.next();
         let six_words: Vec<_> = words.by_ref().take(6).collect();
         debug!("parse_position(): six_words = {:?}", six_words);
-        six_words.join(" ").parse::<Position>().ok()
+        match six_words.join(" ").parse::<Position>() {
+            Ok(pos) => Some(pos),
+            Err(e) => {
+                debug!("parse_position(): invalid fen: {}", e);
+                None
+            },
+        }
     } else {
         None
     };
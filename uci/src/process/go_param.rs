@@ -26,7 +26,10 @@ pub fn setup(state: &mut State, mut data: Vec<GoParam>) {
             GoParam::Nodes(val) => param.nodes = Some(val),
             GoParam::Mate(val) => param.mate = Some(val),
             GoParam::MoveTime(val) => { let _ = timer.exact(val); },
-            GoParam::Infinite => { let _ = timer.infinite(); },
+            GoParam::Infinite => {
+                let _ = timer.infinite();
+                param.infinite = true;
+            },
         }
     }
     state.start_search_time = Some(precise_time_ns());
@@ -5,7 +5,7 @@ use std::thread;
 
 use search;
 use timer::Timer;
-use types::{Cmd, Response, ID_DATA};
+use types::{Cmd, Response, InfoParam, ID_DATA};
 use state::{State, Mode};
 use output::engine_response_output;
 use types::options;
@@ -16,6 +16,11 @@ mod go_param;
 mod time_start;
 mod pos;
 
+fn show_string(output: &SyncSender<Response>, s: String) {
+    output.send(Response::Info(vec![InfoParam::ShowString(s)]))
+          .expect("output channel closed");
+}
+
 pub fn process(state: &mut State,
                cmd: Cmd,
                output: &SyncSender<Response>,
@@ -58,17 +63,36 @@ pub fn process(state: &mut State,
                     match cmd {
                         Cmd::SetOption(val) => {
                             // TODO maybe initialize
-                            state.options.set_value(val);
+                            match val {
+                                options::Value::Hash(size) => {
+                                    if size != state.options.hash_size {
+                                        state.options.set_value(val);
+                                        state.resize_table();
+                                        if state.options.hash_size != size {
+                                            show_string(output, format!(
+                                                "cannot allocate {} MB for the hash table, \
+                                                 using {} MB", size, state.options.hash_size));
+                                        }
+                                        show_string(output, format!(
+                                            "hash table has {} entries",
+                                            state.table.num_entries()));
+                                    }
+                                },
+                                options::Value::ClearHash(_) => state.clear_table(),
+                                _ => state.options.set_value(val),
+                            }
                         },
                         Cmd::UciNewGame => {
-                            state.reset_new_game()
+                            state.clear_table();
+                            state.reset_new_game();
                         },
                         Cmd::SetupPosition(pos, from_to_vec) => {
                             if state.ucinewgame_support {
                                 pos::setup_same(state, pos, from_to_vec);
                                 state.mode = Mode::Ready;
                             } else {
-                                process(state, Cmd::UciNewGame, output, cmd_tx);
+                                // Not a real new game, so the table is kept.
+                                state.reset_new_game();
                                 state.ucinewgame_support = false;
                                 process(state, Cmd::SetupPosition(pos, from_to_vec),
                                         output, cmd_tx);
@@ -87,12 +111,23 @@ pub fn process(state: &mut State,
                     assert!(state.search_state.is_some());
                     if let Cmd::Go(param) = cmd {
                         go_param::setup(state, param);
+                        state.search_id += 1;
+                        let search_id = state.search_id;
                         let (search_tx, search_rx) = sync_channel::<search::Cmd>(0);
-                        let (response_tx, response_rx) = channel::<search::Report>();
+                        let (response_tx, response_rx) = channel::<search::Info>();
                         let search_state = state.search_state.as_ref().unwrap().clone();
+                        let table = state.table.clone();
                         let output = output.clone();
+                        let finish_cmd_tx = cmd_tx.clone();
                         let temp = thread::spawn(move ||
-                            search::start(search_state, search_rx, response_tx));
+                            search::start(search_state, table, search_rx, response_tx, move || {
+                                // The search reached its depth, nodes or mate limit.
+                                // Sent from another thread, since this one must
+                                // stay free to receive search::Cmd::Stop.
+                                thread::spawn(move || {
+                                    let _ = finish_cmd_tx.send(Cmd::StopSearch(search_id));
+                                });
+                            }));
                         thread::spawn(move || engine_response_output(response_rx, output));
 
                         state.search_tx = Some(search_tx);
@@ -120,6 +155,9 @@ pub fn process(state: &mut State,
                             state.start_move_time = Some(precise_time_ns());
                             time_start(state, cmd_tx.clone());
                         },
+                        Cmd::StopSearch(id) if id == state.search_id => {
+                            process(state, Cmd::Stop, output, cmd_tx);
+                        },
                         Cmd::Stop => {
                             state.search_tx.as_ref().unwrap().send(search::Cmd::Stop)
                                  .ok().expect("state.search_tx was closed");
@@ -7,8 +7,14 @@ pub fn setup_new(state: &mut State,
                  mut from_to_vec: Vec<FromTo>) {
     let mut prev_pos = None;
     let mut prev_move = None;
+    let mut history = Vec::new();
+    // Chess960 positions are also recognized from their castling rights.
+    if state.options.chess960 {
+        pos.set_chess960(true);
+    }
     for x in from_to_vec.drain(..) {
         let temp_move = x.to_move_with_pos(&pos);
+        history.push(pos.hash());
         prev_pos = Some(pos.clone());
         pos.make_move(&temp_move);
         prev_move = Some(temp_move);
@@ -17,7 +23,19 @@ pub fn setup_new(state: &mut State,
         pos: pos,
         prev_pos: prev_pos,
         prev_move: prev_move,
-        param: search::Param::new((state.options.hash_size*(1<<20)) as usize),
+        history: history,
+        param: search::Param {
+            multi_pv: state.options.multi_pv as usize,
+            threads: state.options.threads as usize,
+            features: search::Features {
+                null_move: state.options.null_move,
+                lmr: state.options.lmr,
+                futility: state.options.futility,
+                check_extensions: state.options.check_extensions,
+                pvs: state.options.pvs,
+            },
+            ..search::Param::new()
+        },
     });
 }
 
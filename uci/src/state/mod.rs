@@ -1,5 +1,6 @@
 use std::thread::JoinHandle;
 use std::sync::mpsc::SyncSender;
+use std::sync::Arc;
 
 use search;
 use timer::Timer;
@@ -9,37 +10,72 @@ pub use self::mode::Mode;
 
 mod mode;
 
+fn table_size(options: &options::Data) -> usize {
+    (options.hash_size as usize) << 20
+}
+
 pub struct State {
     pub search_state: Option<search::State>,
     pub search_guard: Option<JoinHandle<search::BestMove>>,
     pub search_tx: Option<SyncSender<search::Cmd>>,
+    // Counts the searches, see Cmd::StopSearch.
+    pub search_id: u64,
     pub mode: Mode,
     pub start_search_time: Option<u64>,
     pub start_move_time: Option<u64>,
     pub timer: Timer,
     pub ucinewgame_support: bool,
     pub options: options::Data,
+    // Kept between searches, and only emptied by clear_table().
+    pub table: Arc<search::TranspositionTable>,
 }
 impl State {
     pub fn new() -> State {
+        let options = options::Data::new();
         State {
+            table: Arc::new(search::TranspositionTable::with_size(table_size(&options))),
             search_state: None,
             search_guard: None,
             search_tx: None,
+            search_id: 0,
             mode: Mode::new(),
             start_search_time: None,
             start_move_time: None,
             timer: Timer::new(),
             ucinewgame_support: false,
-            options: options::Data::new(),
+            options: options,
         }
     }
     pub fn reset_new_game(&mut self) {
-        *self = State {
-            mode: Mode::NewGame,
-            ucinewgame_support: true,
-            options: self.options.clone(),
-            ..State::new()
+        self.search_state = None;
+        self.search_guard = None;
+        self.search_tx = None;
+        self.mode = Mode::NewGame;
+        self.start_search_time = None;
+        self.start_move_time = None;
+        self.timer = Timer::new();
+        self.ucinewgame_support = true;
+    }
+    pub fn clear_table(&self) {
+        self.table.clear();
+    }
+    // Called after the Hash option is set.
+    // If that much memory cannot be allocated, the size is halved until it can,
+    // and the size used is stored back into the options.
+    pub fn resize_table(&mut self) {
+        // Free the old table before allocating the new one.
+        self.table = Arc::new(search::TranspositionTable::with_size(0));
+        loop {
+            let size = table_size(&self.options);
+            if let Some(table) = search::TranspositionTable::try_with_size(size) {
+                self.table = Arc::new(table);
+                return;
+            }
+            if self.options.hash_size <= 1 {
+                // Keep the minimal table.
+                return;
+            }
+            self.options.hash_size /= 2;
         }
     }
 }
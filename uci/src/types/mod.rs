@@ -32,6 +32,10 @@ pub enum Cmd {
     Stop,
     PonderHit,
     Quit,
+    // Not sent by the GUI, but by the engine itself when the search with the id
+    // reached its limits or ran out of time. Ignored if that search already stopped,
+    // so that it cannot stop the next one.
+    StopSearch(u64),
 }
 
 #[derive(PartialEq, Eq, Clone, Debug)]
@@ -183,8 +183,19 @@ macro_rules! options_impl {
 }
 
 options_impl!{
-    (5) options
-    Hash(hash_size, "hash"): Spin (1, 1024) = 1,
+    (14) options
+    // In megabytes.
+    Hash(hash_size, "Hash"): Spin (1, 65536) = 16,
+    MultiPv(multi_pv, "MultiPV"): Spin (1, 500) = 1,
+    Threads(threads, "Threads"): Spin (1, 256) = 1,
+    ClearHash(clear_hash, "Clear Hash"): Button () = (),
+    NullMove(null_move, "NullMove"): Check () = true,
+    Lmr(lmr, "LMR"): Check () = true,
+    Futility(futility, "Futility"): Check () = true,
+    CheckExtensions(check_extensions, "CheckExtensions"): Check () = true,
+    Pvs(pvs, "PVS"): Check () = true,
+    // Castling is written as the king taking its own rook.
+    Chess960(chess960, "UCI_Chess960"): Check () = false,
     TestCheck(test_check, "testcheck"): Check () = false,
     TestCombo(test_combo, "testcombo"): Combo ("foo", "bar", "baz") = 0,
     TestButton(test_button, "testbutton"): Button () = (),